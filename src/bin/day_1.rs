@@ -1,123 +1,552 @@
 use advent_of_code_2023::io::read_lines;
 
+use std::{fmt::Display, str::FromStr, time::Instant};
+
+use aho_corasick::AhoCorasick;
+use once_cell::sync::Lazy;
 use regex::{Match, Regex};
 
 const DIGIT_REGEX: &str = r"\d";
-const DIGIT_NAME_REGEX: &str = r"one|two|three|four|five|six|seven|eight|nine";
+const DIGITS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+
+/// The words for zero to nine in each supported language, indexed by value
+const ENGLISH: [&str; 10] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine",
+];
+const GERMAN: [&str; 10] = [
+    "null", "eins", "zwei", "drei", "vier", "fünf", "sechs", "sieben", "acht", "neun",
+];
+const FRENCH: [&str; 10] = [
+    "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf",
+];
+const SPANISH: [&str; 10] = [
+    "cero", "uno", "dos", "tres", "cuatro", "cinco", "seis", "siete", "ocho", "nueve",
+];
 
 fn main() {
-    let file_name = format!("inputs/day_{}.txt", 1);
-    run(read_lines(&file_name));
+    let mut file_name = format!("inputs/day_{}.txt", 1);
+    let mut language = Language::English;
+    let mut zero = false;
+    let mut case_insensitive = false;
+    let mut fast = false;
+    let mut bench_iterations = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--input" => file_name = args.next().expect("--input requires a file name"),
+            "--language" => {
+                let name = args.next().expect("--language requires a language");
+                language = name.parse().unwrap();
+            }
+            "--zero" => zero = true,
+            "--ignore-case" => case_insensitive = true,
+            "--fast" => fast = true,
+            "--bench" => {
+                let iterations = args
+                    .next()
+                    .expect("--bench requires a number of iterations");
+                bench_iterations = Some(iterations.parse::<u32>().unwrap());
+            }
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+
+    let vocabulary = language.vocabulary(zero).case_insensitive(case_insensitive);
+    let simple = SimpleCalibrationParser::new();
+    let advanced = AdvancedCalibrationParser::new(&vocabulary);
+    let fast_simple = FastCalibrationParser::digits_only();
+    let fast_advanced = FastCalibrationParser::new(&vocabulary);
+
+    if let Some(iterations) = bench_iterations {
+        let lines = read_lines(&file_name).collect::<Vec<_>>();
+        benchmark(
+            &lines,
+            &[
+                ("simple", &simple),
+                ("fast simple", &fast_simple),
+                ("advanced", &advanced),
+                ("fast advanced", &fast_advanced),
+            ],
+            iterations,
+        );
+    } else if fast {
+        run(read_lines(&file_name), &[&fast_simple, &fast_advanced]);
+    } else {
+        run(read_lines(&file_name), &[&simple, &advanced]);
+    }
 }
 
-pub fn run<'a, T>(inputs: T)
+/// Prints the total calibration value for each parser, one star per parser
+fn run<T>(inputs: T, parsers: &[&dyn CalibrationParser])
 where
     T: Iterator<Item = String>,
 {
-    let parser_1 = SimpleCalibrationParser::new();
-    let parser_2 = AdvancedCalibrationParser::new();
+    let lines = inputs.collect::<Vec<_>>();
 
-    let mut calib_1 = 0;
-    let mut calib_2 = 0;
+    for (i, report) in calibration_reports(&lines, parsers).iter().enumerate() {
+        println!("Day 1, Star {}: {}", i + 1, report.total());
+        print!("{}", report.skipped_summary());
+    }
+}
+
+fn calibration_reports<T: AsRef<str>>(
+    lines: &[T],
+    parsers: &[&dyn CalibrationParser],
+) -> Vec<CalibrationReport> {
+    parsers
+        .iter()
+        .map(|parser| {
+            lines
+                .iter()
+                .map(|line| parser.parse_line(line.as_ref()))
+                .collect()
+        })
+        .collect()
+}
 
-    for line in inputs {
-        if let Some(i1) = parser_1.parse_line(&line) {
-            calib_1 += i1;
+/// Times each parser over every line, averaged over the given number of passes
+fn benchmark<T: AsRef<str>>(
+    lines: &[T],
+    parsers: &[(&str, &dyn CalibrationParser)],
+    iterations: u32,
+) {
+    for (name, parser) in parsers {
+        let start = Instant::now();
+        let mut total = 0;
+        for _ in 0..iterations {
+            total = lines
+                .iter()
+                .filter_map(|line| parser.parse_line(line.as_ref()).ok())
+                .map(|calibration| calibration.value)
+                .sum::<i32>();
         }
-        if let Some(i2) = parser_2.parse_line(&line) {
-            calib_2 += i2;
+        let elapsed = start.elapsed() / iterations.max(1);
+        println!("{:<14} {:>12.2?} per pass, total {}", name, elapsed, total);
+    }
+}
+
+/// Reads the calibration value of a line from its first and last digits.
+///
+/// Implementations only decide what counts as a digit by finding the first
+/// and last digit tokens.
+trait CalibrationParser {
+    fn find_tokens(&self, line: &str) -> Result<(DigitMatch, DigitMatch), CalibrationError>;
+
+    fn parse_line(&self, line: &str) -> Result<Calibration, CalibrationError> {
+        let (first, last) = self.find_tokens(line)?;
+        Calibration::new(first, last)
+    }
+}
+
+/// The calibration value of a line, and the first and last digits it was
+/// made from
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Calibration {
+    value: i32,
+    first: DigitMatch,
+    last: DigitMatch,
+}
+
+impl Calibration {
+    fn new(first: DigitMatch, last: DigitMatch) -> Result<Calibration, CalibrationError> {
+        let digits = format!("{}{}", first.value, last.value);
+        let value = digits
+            .parse::<i32>()
+            .map_err(|_| CalibrationError::Unparsable(digits))?;
+
+        Ok(Calibration { value, first, last })
+    }
+}
+
+/// Why a line has no calibration value
+#[derive(Debug, Clone, PartialEq)]
+enum CalibrationError {
+    NoDigits,
+    /// The matched text or combined digits could not be read as a number
+    Unparsable(String),
+}
+
+impl Display for CalibrationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CalibrationError::NoDigits => write!(f, "no digits"),
+            CalibrationError::Unparsable(text) => write!(f, "could not parse {:?}", text),
         }
     }
+}
 
-    println!("Day 1, Star 1: {}", calib_1);
-    println!("Day 1, Star 2: {}", calib_2);
+/// The calibration result of every line in a document
+struct CalibrationReport {
+    lines: Vec<Result<Calibration, CalibrationError>>,
+}
+
+impl CalibrationReport {
+    /// The sum of every calibration value, ignoring skipped lines
+    fn total(&self) -> i32 {
+        self.lines
+            .iter()
+            .filter_map(|line| line.as_ref().ok())
+            .map(|calibration| calibration.value)
+            .sum()
+    }
+
+    /// The line numbers, starting from 1, of lines without a calibration
+    /// value and why they were skipped
+    fn skipped(&self) -> impl Iterator<Item = (usize, &CalibrationError)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| line.as_ref().err().map(|err| (i + 1, err)))
+    }
+
+    /// Lists every skipped line, or nothing if no lines were skipped
+    fn skipped_summary(&self) -> String {
+        let skipped = self.skipped().collect::<Vec<_>>();
+        if skipped.is_empty() {
+            return String::new();
+        }
+
+        let mut result = format!("Skipped {} of {} lines\n", skipped.len(), self.lines.len());
+        for (line_no, err) in skipped {
+            result += &format!("  line {}: {}\n", line_no, err);
+        }
+        result
+    }
+}
+
+impl FromIterator<Result<Calibration, CalibrationError>> for CalibrationReport {
+    fn from_iter<T: IntoIterator<Item = Result<Calibration, CalibrationError>>>(iter: T) -> Self {
+        CalibrationReport {
+            lines: iter.into_iter().collect(),
+        }
+    }
 }
 
 fn parse_digit<'a, T: Into<&'a str>>(m: T) -> Option<u32> {
-    match m.into() {
-        "one" => return Option::Some(1),
-        "two" => return Option::Some(2),
-        "three" => return Option::Some(3),
-        "four" => return Option::Some(4),
-        "five" => return Option::Some(5),
-        "six" => return Option::Some(6),
-        "seven" => return Option::Some(7),
-        "eight" => return Option::Some(8),
-        "nine" => return Option::Some(9),
-        c => c.parse::<u32>().map_or(Option::None, Option::Some),
+    m.into().parse::<u32>().ok()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Language {
+    English,
+    German,
+    French,
+    Spanish,
+}
+
+impl Language {
+    /// The words for one to nine, and optionally zero, in this language
+    fn vocabulary(&self, zero: bool) -> Vocabulary {
+        let words = match self {
+            Language::English => ENGLISH,
+            Language::German => GERMAN,
+            Language::French => FRENCH,
+            Language::Spanish => SPANISH,
+        };
+        let start = if zero { 0 } else { 1 };
+
+        Vocabulary::new((start..words.len()).map(|value| (words[value], value as u32)))
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "english" => Ok(Language::English),
+            "german" => Ok(Language::German),
+            "french" => Ok(Language::French),
+            "spanish" => Ok(Language::Spanish),
+            _ => Err(format!("Unsupported language {}", s)),
+        }
+    }
+}
+
+/// The words that can stand in for a digit, and the values they stand for
+#[derive(Debug, Clone, PartialEq)]
+struct Vocabulary {
+    words: Vec<(String, u32)>,
+    case_insensitive: bool,
+}
+
+impl Vocabulary {
+    fn new<'a, T: IntoIterator<Item = (&'a str, u32)>>(words: T) -> Vocabulary {
+        Vocabulary {
+            words: words
+                .into_iter()
+                .map(|(word, value)| (word.to_string(), value))
+                .collect(),
+            case_insensitive: false,
+        }
+    }
+
+    /// Every word to search for, with upper case copies of non-ASCII words
+    /// when ignoring case, since only ASCII letters are case folded
+    fn patterns(&self) -> Vec<(String, u32)> {
+        let mut patterns = self.words.clone();
+        if self.case_insensitive {
+            let upper = self
+                .words
+                .iter()
+                .filter(|(word, _)| !word.is_ascii())
+                .map(|(word, value)| (word.to_uppercase(), *value))
+                .collect::<Vec<_>>();
+            patterns.extend(upper);
+        }
+        patterns
+    }
+
+    /// Whether words match regardless of case. ASCII letters can be in any
+    /// case, while other letters must be all lower or all upper case.
+    fn case_insensitive(mut self, case_insensitive: bool) -> Vocabulary {
+        self.case_insensitive = case_insensitive;
+        self
+    }
+}
+
+impl Default for Vocabulary {
+    fn default() -> Self {
+        Language::English.vocabulary(false)
     }
 }
 
 struct SimpleCalibrationParser(Regex);
 
+static SIMPLE_PARSER: Lazy<SimpleCalibrationParser> = Lazy::new(SimpleCalibrationParser::new);
+
 impl SimpleCalibrationParser {
     fn new() -> SimpleCalibrationParser {
         let re = Regex::new(DIGIT_REGEX).unwrap();
         SimpleCalibrationParser(re)
     }
 
-    pub fn parse_line(&self, line: &str) -> Option<i32> {
+    /// `\d` matches any Unicode digit, but only ASCII digits can be parsed
+    fn digit_match(m: Match) -> Result<DigitMatch, CalibrationError> {
+        let value = parse_digit(m).ok_or(CalibrationError::Unparsable(m.as_str().into()))?;
+        Ok(DigitMatch {
+            value,
+            start: m.start(),
+            end: m.end(),
+        })
+    }
+}
+
+impl CalibrationParser for SimpleCalibrationParser {
+    fn find_tokens(&self, line: &str) -> Result<(DigitMatch, DigitMatch), CalibrationError> {
         let matches: Vec<Match> = self.0.find_iter(line).collect();
 
-        if matches.len() < 1 {
-            return Option::None;
+        if matches.is_empty() {
+            return Err(CalibrationError::NoDigits);
         }
 
-        let first_digit = parse_digit(matches[0])?;
-        let last_digit = parse_digit(matches[matches.len() - 1])?;
+        let first_digit = Self::digit_match(matches[0])?;
+        let last_digit = Self::digit_match(matches[matches.len() - 1])?;
 
-        let config_value = format!("{}{}", first_digit, last_digit)
-            .parse::<i32>()
-            .map_or(None, Some)?;
-
-        Some(config_value)
+        Ok((first_digit, last_digit))
     }
 }
 
-struct AdvancedCalibrationParser {
-    regex: Regex,
-    rev_regex: Regex,
+/// A digit or digit name found in a line, with its byte span
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct DigitMatch {
+    value: u32,
+    start: usize,
+    end: usize,
 }
 
-fn reversed_str<'a, T: Into<&'a str>>(s: T) -> String {
-    s.into().chars().rev().collect::<String>()
+/// Finds every digit and digit name in a single pass, including ones that
+/// overlap such as the `one` and `eight` in `oneight`.
+struct DigitMatcher {
+    automaton: AhoCorasick,
+    values: Vec<u32>,
 }
 
+impl DigitMatcher {
+    fn new(vocabulary: &Vocabulary) -> DigitMatcher {
+        let patterns = DIGITS
+            .iter()
+            .map(|&digit| (digit.to_string(), parse_digit(digit).unwrap()))
+            .chain(vocabulary.patterns())
+            .collect::<Vec<_>>();
+
+        let automaton = AhoCorasick::builder()
+            .ascii_case_insensitive(vocabulary.case_insensitive)
+            .build(patterns.iter().map(|(pattern, _)| pattern))
+            .unwrap();
+
+        DigitMatcher {
+            automaton,
+            values: patterns.into_iter().map(|(_, value)| value).collect(),
+        }
+    }
+
+    /// Every match in the line, overlapping ones included, in the order they
+    /// end. Matches ending at the same place come shortest first.
+    pub fn all<'a>(&'a self, line: &'a str) -> impl Iterator<Item = DigitMatch> + 'a {
+        self.automaton
+            .find_overlapping_iter(line)
+            .map(|m| DigitMatch {
+                value: self.values[m.pattern().as_usize()],
+                start: m.start(),
+                end: m.end(),
+            })
+    }
+
+    /// The matches that start first and last in the line, found in one pass
+    /// over `all`. Of two matches starting at the same place the first is
+    /// the shorter and the last is the longer.
+    fn first_and_last(&self, line: &str) -> Option<(DigitMatch, DigitMatch)> {
+        let mut result: Option<(DigitMatch, DigitMatch)> = None;
+        for found in self.all(line) {
+            result = Some(match result {
+                None => (found, found),
+                Some((first, last)) => (
+                    if found.start < first.start {
+                        found
+                    } else {
+                        first
+                    },
+                    if found.start >= last.start {
+                        found
+                    } else {
+                        last
+                    },
+                ),
+            });
+        }
+        result
+    }
+}
+
+struct AdvancedCalibrationParser(DigitMatcher);
+
 impl AdvancedCalibrationParser {
-    fn new() -> AdvancedCalibrationParser {
-        let regex_str = format!(r"\d|{}", DIGIT_NAME_REGEX);
+    fn new(vocabulary: &Vocabulary) -> AdvancedCalibrationParser {
+        AdvancedCalibrationParser(DigitMatcher::new(vocabulary))
+    }
+}
 
-        // We reverse the regex so that we can find the last instance.
-        let reverse_name_regex_str = reversed_str(DIGIT_NAME_REGEX);
-        // Single digit detection doesn't need to be reversed
-        let reverse_name_str = format!(r"\d|{}", &reverse_name_regex_str);
+impl CalibrationParser for AdvancedCalibrationParser {
+    fn find_tokens(&self, line: &str) -> Result<(DigitMatch, DigitMatch), CalibrationError> {
+        self.0
+            .first_and_last(line)
+            .ok_or(CalibrationError::NoDigits)
+    }
+}
 
-        AdvancedCalibrationParser {
-            regex: Regex::new(&regex_str).unwrap(),
-            rev_regex: Regex::new(&reverse_name_str).unwrap(),
+/// Scans the raw bytes of a line from each end for the first and last ASCII
+/// digit or word, without allocating. It finds the same tokens as the regex
+/// parsers: of two tokens starting at the same place, the first is the
+/// shorter and the last is the longer, as with `DigitMatcher`.
+///
+/// Lines that only need digits are a single `is_ascii_digit` scan, which the
+/// compiler can vectorise. `\d` also matches other Unicode digits, so lines
+/// that aren't ASCII go through `SimpleCalibrationParser` instead, which
+/// reports those digits as unparsable.
+struct FastCalibrationParser {
+    words: Vec<(Vec<u8>, u32)>,
+    case_insensitive: bool,
+}
+
+impl FastCalibrationParser {
+    fn new(vocabulary: &Vocabulary) -> FastCalibrationParser {
+        FastCalibrationParser {
+            words: vocabulary
+                .patterns()
+                .into_iter()
+                .map(|(word, value)| (word.into_bytes(), value))
+                .collect(),
+            case_insensitive: vocabulary.case_insensitive,
         }
     }
 
-    pub fn parse_line(&self, line: &str) -> Option<i32> {
-        let first_match = self.regex.find(line)?;
+    fn digits_only() -> FastCalibrationParser {
+        FastCalibrationParser {
+            words: vec![],
+            case_insensitive: false,
+        }
+    }
 
-        let reverse_line = reversed_str(line);
-        let last_match = self.rev_regex.find(&reverse_line)?;
+    /// The shortest or longest digit or word starting at `start`, if there
+    /// is one. Of tokens the same length, the shortest is the earliest
+    /// pattern and the longest is the latest, with digits before words.
+    fn token_at(&self, bytes: &[u8], start: usize, longest: bool) -> Option<DigitMatch> {
+        let rest = &bytes[start..];
+        let digit = rest
+            .first()
+            .filter(|b| b.is_ascii_digit())
+            .map(|b| (1, (b - b'0') as u32));
+        let words = self
+            .words
+            .iter()
+            .filter(|(word, _)| {
+                rest.len() >= word.len()
+                    && if self.case_insensitive {
+                        rest[..word.len()].eq_ignore_ascii_case(word)
+                    } else {
+                        rest.starts_with(word)
+                    }
+            })
+            .map(|(word, value)| (word.len(), *value));
+
+        let mut best: Option<(usize, u32)> = None;
+        for (len, value) in digit.into_iter().chain(words) {
+            let better = best.is_none_or(|(best_len, _)| {
+                if longest {
+                    len >= best_len
+                } else {
+                    len < best_len
+                }
+            });
+            if better {
+                best = Some((len, value));
+            }
+        }
 
-        let first_digit = parse_digit(first_match.as_str())?;
-        let last_digit = parse_digit(&*reversed_str(last_match))?;
+        best.map(|(len, value)| DigitMatch {
+            value,
+            start,
+            end: start + len,
+        })
+    }
+}
 
-        let config_value = format!("{}{}", first_digit, last_digit)
-            .parse::<i32>()
-            .map_or(None, Some)?;
+impl CalibrationParser for FastCalibrationParser {
+    fn find_tokens(&self, line: &str) -> Result<(DigitMatch, DigitMatch), CalibrationError> {
+        let bytes = line.as_bytes();
+
+        if self.words.is_empty() {
+            if !line.is_ascii() {
+                return SIMPLE_PARSER.find_tokens(line);
+            }
+            let first = bytes.iter().position(u8::is_ascii_digit);
+            let last = bytes.iter().rposition(u8::is_ascii_digit);
+            return match (first, last) {
+                (Some(first), Some(last)) => Ok((
+                    self.token_at(bytes, first, false).unwrap(),
+                    self.token_at(bytes, last, true).unwrap(),
+                )),
+                _ => Err(CalibrationError::NoDigits),
+            };
+        }
 
-        Some(config_value)
+        let first = (0..bytes.len()).find_map(|i| self.token_at(bytes, i, false));
+        let last = (0..bytes.len())
+            .rev()
+            .find_map(|i| self.token_at(bytes, i, true));
+        match (first, last) {
+            (Some(first), Some(last)) => Ok((first, last)),
+            _ => Err(CalibrationError::NoDigits),
+        }
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use advent_of_code_2023::random::XorShift;
 
     const INPUT_1: &str = "1abc2
     pqr3stu8vwx
@@ -129,8 +558,8 @@ mod tests {
         let parser = SimpleCalibrationParser::new();
         let result = INPUT_1
             .lines()
-            .map(|s| parser.parse_line(s))
-            .filter_map(|s| s)
+            .filter_map(|s| parser.parse_line(s).ok())
+            .map(|c| c.value)
             .collect::<Vec<i32>>();
 
         assert_eq!(vec![12, 38, 15, 77], result);
@@ -146,11 +575,11 @@ mod tests {
 
     #[test]
     fn advancedcalibration_parser_parses_digit_names() {
-        let parser = AdvancedCalibrationParser::new();
+        let parser = AdvancedCalibrationParser::new(&Vocabulary::default());
         let result = INPUT_2
             .lines()
-            .map(|s| parser.parse_line(s))
-            .filter_map(|s| s)
+            .filter_map(|s| parser.parse_line(s).ok())
+            .map(|c| c.value)
             .collect::<Vec<i32>>();
 
         assert_eq!(vec![29, 83, 13, 24, 42, 14, 76], result);
@@ -159,11 +588,11 @@ mod tests {
 
     #[test]
     fn advancedcalibration_parser_parses_digit_chars() {
-        let parser = AdvancedCalibrationParser::new();
+        let parser = AdvancedCalibrationParser::new(&Vocabulary::default());
         let result = INPUT_1
             .lines()
-            .map(|s| parser.parse_line(s))
-            .filter_map(|s| s)
+            .filter_map(|s| parser.parse_line(s).ok())
+            .map(|c| c.value)
             .collect::<Vec<i32>>();
 
         assert_eq!(vec![12, 38, 15, 77], result);
@@ -171,9 +600,306 @@ mod tests {
 
     #[test]
     fn advancedcalibration_parser_parses_digit_names_with_overlap() {
-        let parser = AdvancedCalibrationParser::new();
+        let parser = AdvancedCalibrationParser::new(&Vocabulary::default());
         let result = parser.parse_line("28gtbkszmrtmnineoneightmx");
 
-        assert_eq!(result, Some(28));
+        assert_eq!(result.map(|c| c.value), Ok(28));
+
+        let matcher = DigitMatcher::new(&Vocabulary::default());
+        assert_eq!(
+            matcher.all("oneight").collect::<Vec<_>>(),
+            vec![
+                DigitMatch {
+                    value: 1,
+                    start: 0,
+                    end: 3
+                },
+                DigitMatch {
+                    value: 8,
+                    start: 2,
+                    end: 7
+                },
+            ]
+        );
+        assert_eq!(
+            matcher
+                .all("28gtbkszmrtmnineoneightmx")
+                .map(|m| m.value)
+                .collect::<Vec<_>>(),
+            vec![2, 8, 9, 1, 8]
+        );
+        assert_eq!(matcher.all("abc").next(), None);
+        assert_eq!(
+            matcher.first_and_last("oneight"),
+            Some((
+                DigitMatch {
+                    value: 1,
+                    start: 0,
+                    end: 3
+                },
+                DigitMatch {
+                    value: 8,
+                    start: 2,
+                    end: 7
+                },
+            ))
+        );
+        let values = |line| {
+            matcher
+                .first_and_last(line)
+                .map(|(f, l)| (f.value, l.value))
+        };
+        assert_eq!(values("xtwone3four"), Some((2, 4)));
+        assert_eq!(values("zoneight"), Some((1, 8)));
+        assert_eq!(values("28gtbkszmrtmnineoneightmx"), Some((2, 8)));
+        assert_eq!(values("abc"), None);
+    }
+
+    #[test]
+    fn advancedcalibration_parser_parses_other_languages() {
+        let tests = [
+            (Language::German, "zweiundvierzig", Ok(24)),
+            (Language::German, "xfünfzehnachtx", Ok(58)),
+            (Language::French, "quatrevingtdix", Ok(44)),
+            (Language::French, "unedeuxtroisx", Ok(13)),
+            (Language::Spanish, "xcincoynueve", Ok(59)),
+            (
+                Language::Spanish,
+                "one two three",
+                Err(CalibrationError::NoDigits),
+            ),
+        ];
+
+        for (language, line, expected) in tests {
+            let parser = AdvancedCalibrationParser::new(&language.vocabulary(false));
+            assert_eq!(
+                parser.parse_line(line).map(|c| c.value),
+                expected,
+                "{} in {:?}",
+                line,
+                language
+            );
+        }
+    }
+
+    #[test]
+    fn advancedcalibration_parser_parses_zero() {
+        let without_zero = AdvancedCalibrationParser::new(&Language::English.vocabulary(false));
+        assert_eq!(without_zero.parse_line("zero5").map(|c| c.value), Ok(55));
+
+        let with_zero = AdvancedCalibrationParser::new(&Language::English.vocabulary(true));
+        assert_eq!(with_zero.parse_line("zero5").map(|c| c.value), Ok(5));
+        assert_eq!(with_zero.parse_line("7cero").map(|c| c.value), Ok(77));
+    }
+
+    #[test]
+    fn advancedcalibration_parser_ignores_case() {
+        let sensitive = AdvancedCalibrationParser::new(&Vocabulary::default());
+        assert_eq!(sensitive.parse_line("FIVEtwo").map(|c| c.value), Ok(22));
+
+        let insensitive =
+            AdvancedCalibrationParser::new(&Vocabulary::default().case_insensitive(true));
+        assert_eq!(insensitive.parse_line("FIVEtwo").map(|c| c.value), Ok(52));
+        assert_eq!(insensitive.parse_line("sIx").map(|c| c.value), Ok(66));
+
+        let german = AdvancedCalibrationParser::new(
+            &Language::German.vocabulary(false).case_insensitive(true),
+        );
+        assert_eq!(german.parse_line("FÜNFAcht").map(|c| c.value), Ok(58));
+        assert_eq!(german.parse_line("Fünf").map(|c| c.value), Ok(55));
+    }
+
+    #[test]
+    fn parse_line_reports_digit_spans() {
+        let parser = AdvancedCalibrationParser::new(&Vocabulary::default());
+        assert_eq!(
+            parser.parse_line("two1nine"),
+            Ok(Calibration {
+                value: 29,
+                first: DigitMatch {
+                    value: 2,
+                    start: 0,
+                    end: 3
+                },
+                last: DigitMatch {
+                    value: 9,
+                    start: 4,
+                    end: 8
+                },
+            })
+        );
+
+        let parser = SimpleCalibrationParser::new();
+        assert_eq!(
+            parser
+                .parse_line("pqr3stu8vwx")
+                .map(|c| (c.first.start, c.last.start)),
+            Ok((3, 7))
+        );
+    }
+
+    #[test]
+    fn parse_line_reports_failures() {
+        let simple = SimpleCalibrationParser::new();
+        assert_eq!(simple.parse_line("abc"), Err(CalibrationError::NoDigits));
+        assert_eq!(
+            simple.parse_line("a٣b"),
+            Err(CalibrationError::Unparsable("٣".into()))
+        );
+
+        let vocabulary = Vocabulary::new([("huge", 3_000_000_000)]);
+        let advanced = AdvancedCalibrationParser::new(&vocabulary);
+        assert_eq!(advanced.parse_line("nine"), Err(CalibrationError::NoDigits));
+        assert_eq!(
+            advanced.parse_line("huge"),
+            Err(CalibrationError::Unparsable("30000000003000000000".into()))
+        );
+    }
+
+    #[test]
+    fn calibration_report_summarises_skipped_lines() {
+        let parser = SimpleCalibrationParser::new();
+        let report = "1abc2
+        no digits here
+        treb7uchet
+        "
+        .lines()
+        .map(|line| parser.parse_line(line))
+        .collect::<CalibrationReport>();
+
+        assert_eq!(report.total(), 89);
+        assert_eq!(
+            report.skipped().collect::<Vec<_>>(),
+            vec![
+                (2, &CalibrationError::NoDigits),
+                (4, &CalibrationError::NoDigits)
+            ]
+        );
+        assert_eq!(
+            report.skipped_summary(),
+            "Skipped 2 of 4 lines\n  line 2: no digits\n  line 4: no digits\n"
+        );
+    }
+
+    #[test]
+    fn calibration_reports_cover_every_parser() {
+        let simple = SimpleCalibrationParser::new();
+        let english = AdvancedCalibrationParser::new(&Vocabulary::default());
+        let with_zero = AdvancedCalibrationParser::new(&Language::English.vocabulary(true));
+        let lines = ["two1nine", "zero7", "xyz"];
+
+        let totals = calibration_reports(&lines, &[&simple, &english, &with_zero])
+            .iter()
+            .map(|report| report.total())
+            .collect::<Vec<_>>();
+
+        assert_eq!(totals, vec![11 + 77, 29 + 77, 29 + 7]);
+    }
+
+    /// Generates lines from fragments of digit words in every language, so
+    /// that words often overlap or are cut short, with the odd non-ASCII digit
+    fn generated_lines(count: usize) -> Vec<String> {
+        const FRAGMENTS: [&str; 16] = [
+            "on", "e", "tw", "o", "thr", "ee", "ight", "nin", "fünf", "zé", "ro", "un", "x", "7",
+            "٣", "७",
+        ];
+        // A fixed seed keeps failures reproducible
+        let mut rng = XorShift::new(0x2545_F491_4F6C_DD1D);
+
+        let words = [ENGLISH, GERMAN, FRENCH, SPANISH].concat();
+        (0..count)
+            .map(|_| {
+                let len = rng.below(8);
+                (0..len)
+                    .map(|_| match rng.below(4) {
+                        0 => words[rng.below(words.len())].to_string(),
+                        1 => words[rng.below(words.len())].to_uppercase(),
+                        2 => DIGITS[rng.below(DIGITS.len())].to_string(),
+                        _ => FRAGMENTS[rng.below(FRAGMENTS.len())].to_string(),
+                    })
+                    .collect::<String>()
+            })
+            .collect()
+    }
+
+    /// Words that start with each other or with a digit, so that several
+    /// tokens can start at the same place
+    const PREFIXED: [(&str, u32); 6] = [
+        ("on", 7),
+        ("one", 1),
+        ("oneight", 9),
+        ("eight", 8),
+        ("7e", 3),
+        ("7eight", 5),
+    ];
+
+    #[test]
+    fn fastcalibration_parser_matches_regex_parsers() {
+        let lines = generated_lines(2000);
+
+        let simple = SimpleCalibrationParser::new();
+        let fast_simple = FastCalibrationParser::digits_only();
+        for line in &lines {
+            assert_eq!(
+                fast_simple.parse_line(line),
+                simple.parse_line(line),
+                "{}",
+                line
+            );
+        }
+
+        for language in [
+            Language::English,
+            Language::German,
+            Language::French,
+            Language::Spanish,
+        ] {
+            for zero in [false, true] {
+                for case_insensitive in [false, true] {
+                    let vocabulary = language.vocabulary(zero).case_insensitive(case_insensitive);
+                    let advanced = AdvancedCalibrationParser::new(&vocabulary);
+                    let fast = FastCalibrationParser::new(&vocabulary);
+
+                    for line in &lines {
+                        assert_eq!(
+                            fast.parse_line(line),
+                            advanced.parse_line(line),
+                            "{} with {:?}",
+                            line,
+                            vocabulary
+                        );
+                    }
+                }
+            }
+        }
+
+        for case_insensitive in [false, true] {
+            let vocabulary = Vocabulary::new(PREFIXED).case_insensitive(case_insensitive);
+            let advanced = AdvancedCalibrationParser::new(&vocabulary);
+            let fast = FastCalibrationParser::new(&vocabulary);
+
+            for line in &lines {
+                assert_eq!(
+                    fast.parse_line(line),
+                    advanced.parse_line(line),
+                    "{} with {:?}",
+                    line,
+                    vocabulary
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_fastcalibration_parser_same_start() {
+        let fast = FastCalibrationParser::new(&Vocabulary::new(PREFIXED));
+        assert_eq!(fast.parse_line("onexone").map(|c| c.value), Ok(71));
+        assert_eq!(fast.parse_line("7ex7e").map(|c| c.value), Ok(73));
+
+        let digits = FastCalibrationParser::digits_only();
+        assert_eq!(
+            digits.parse_line("a٣b7"),
+            Err(CalibrationError::Unparsable("٣".into()))
+        );
     }
 }
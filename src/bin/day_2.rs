@@ -1,136 +1,729 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    fmt::Display,
+    str::FromStr,
+};
+
 use advent_of_code_2023::io::read_lines;
 use once_cell::sync::Lazy;
 use regex::Regex;
+use serde::{Deserialize, Serialize};
 
 fn main() {
-    let max_val_hand = Hand {
-        red: 12,
-        blue: 14,
-        green: 13,
-    };
+    let mut max_val_hand = Hand::new([("red", 12), ("blue", 14), ("green", 13)]);
+    let mut report = false;
+    let mut mode = ParseMode::Lenient;
+    let mut probabilities = false;
+    let mut estimate_size = None;
+    let mut export = None;
+    let mut import = None;
 
-    let mut sum_valid_game_ids = 0;
-    let mut sum_powers = 0;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bag" => max_val_hand = Hand::from(&args.next().expect("--bag requires cubes")),
+            "--report" => report = true,
+            "--strict" => mode = ParseMode::Strict,
+            "--probabilities" => probabilities = true,
+            "--estimate" => {
+                let size = args.next().expect("--estimate requires a bag size");
+                estimate_size = Some(size.parse::<i32>().unwrap());
+            }
+            "--export" => {
+                let format = args.next().expect("--export requires json or csv");
+                export = Some(format.parse::<RecordFormat>().unwrap());
+            }
+            "--import" => import = Some(args.next().expect("--import requires a file")),
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
 
-    for line in read_lines("inputs/day_2.txt") {
-        let game = Game::from(&line);
-        if game.is_valid(&max_val_hand) {
-            sum_valid_game_ids += game.id;
+    let games = match import {
+        Some(path) => import_games(&path).unwrap_or_else(|err| {
+            eprintln!("{}: {}", path, err);
+            std::process::exit(1);
+        }),
+        None => {
+            let mut games = vec![];
+            for (i, line) in read_lines("inputs/day_2.txt").enumerate() {
+                match Game::parse(&line, mode) {
+                    Ok(game) => games.push(game),
+                    Err(err) => {
+                        eprintln!("Line {}, {}", i + 1, err);
+                        std::process::exit(1);
+                    }
+                }
+            }
+            games
         }
+    };
 
-        sum_powers += game.min_val_hand().power();
+    if let Some(format) = export {
+        print!("{}", export_games(&games, &max_val_hand, format).unwrap());
+        return;
     }
 
+    let sum_valid_game_ids = feasible_games(&games, &max_val_hand).iter().sum::<i32>();
+    let sum_powers = games
+        .iter()
+        .map(|game| game.min_val_hand().power_over(max_val_hand.colors()))
+        .sum::<i32>();
+
     println!("Day 2, Star 1: {}", sum_valid_game_ids);
     println!("Day 2, Star 2: {}", sum_powers);
+
+    if report {
+        print!("{}", bag_report(&games, &max_val_hand));
+    }
+
+    if probabilities {
+        for game in &games {
+            println!(
+                "Game {}: {:e} with replacement, {:e} without",
+                game.id,
+                game.probability(&max_val_hand, Replacement::With),
+                game.probability(&max_val_hand, Replacement::Without)
+            );
+        }
+    }
+
+    if let Some(size) = estimate_size {
+        for replacement in [Replacement::With, Replacement::Without] {
+            match most_likely_bag(&games, size, replacement) {
+                Some(bag) => println!("Most likely bag {:?}: {}", replacement, bag),
+                None => println!("No bag of {} cubes can produce every game", size),
+            }
+        }
+    }
 }
 
-#[derive(PartialEq, Debug)]
+/// The smallest bag from which every game is possible
+fn minimal_bag(games: &[Game]) -> Hand {
+    let mut bag = Hand::default();
+    for game in games {
+        let min_hand = game.min_val_hand();
+        for color in min_hand.colors() {
+            if min_hand.get(color) > bag.get(color) {
+                bag.set(color, min_hand.get(color));
+            }
+        }
+    }
+    bag
+}
+
+/// The ids of every game that is possible with the given bag
+fn feasible_games(games: &[Game], bag: &Hand) -> BTreeSet<i32> {
+    games
+        .iter()
+        .filter(|game| game.is_valid(bag))
+        .map(|game| game.id)
+        .collect()
+}
+
+/// Describes the minimal bag for all games, which games the given bag allows
+/// and the draws that set each game's minimum for every colour
+fn bag_report(games: &[Game], bag: &Hand) -> String {
+    let feasible = feasible_games(games, bag);
+    let mut result = format!("Minimal bag: {}\n", minimal_bag(games));
+    result += &format!(
+        "Possible with {}: {} of {} games {:?}\n",
+        bag,
+        feasible.len(),
+        games.len(),
+        feasible
+    );
+
+    for game in games {
+        let constraints = game
+            .tightest_constraints()
+            .iter()
+            .map(|c| format!("{} {} (draw {})", c.count, c.color, c.draw + 1))
+            .collect::<Vec<_>>();
+        result += &format!("Game {}: {}\n", game.id, constraints.join(", "));
+    }
+
+    result
+}
+
+/// A set of cubes, counted by colour. Colours that aren't in the set have a
+/// count of zero. Serialized as a map from colour to count.
+#[derive(PartialEq, Debug, Default, Clone, Serialize, Deserialize)]
+#[serde(from = "BTreeMap<String, i32>", into = "BTreeMap<String, i32>")]
 struct Hand {
-    red: i32,
-    blue: i32,
-    green: i32,
+    cubes: BTreeMap<String, i32>,
+}
+
+/// How to treat draws that don't follow the puzzle's format
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ParseMode {
+    /// Reject repeated colours, empty draws and any text that isn't a count
+    /// and colour separated by commas
+    Strict,
+    /// Sum repeated colours and skip over anything else
+    Lenient,
+}
+
+/// Why a game couldn't be parsed, and the byte offset in the line where the
+/// problem starts. Colours can be any Unicode word, so this is only the
+/// column when the line is ASCII.
+#[derive(Debug, PartialEq)]
+struct ParseError {
+    position: usize,
+    kind: ParseErrorKind,
 }
 
-static HAND_REGEX: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?<val>\d+) (?<color>red|blue|green)").unwrap());
+#[derive(Debug, PartialEq)]
+enum ParseErrorKind {
+    /// The line doesn't start with `Game <id>: `
+    MissingGameId,
+    EmptyDraw,
+    DuplicateColor(String),
+    UnexpectedText(String),
+    InvalidCount(String),
+}
+
+impl Error for ParseError {}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "byte {}: ", self.position)?;
+        match &self.kind {
+            ParseErrorKind::MissingGameId => write!(f, "expected \"Game <id>: \""),
+            ParseErrorKind::EmptyDraw => write!(f, "empty draw"),
+            ParseErrorKind::DuplicateColor(color) => write!(f, "{} appears more than once", color),
+            ParseErrorKind::UnexpectedText(text) => write!(f, "unexpected {:?}", text),
+            ParseErrorKind::InvalidCount(count) => write!(f, "{} is not a valid count", count),
+        }
+    }
+}
+
+static SEPARATOR_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"^\s*,\s*$").unwrap());
+
+static HAND_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?<val>\d+) (?<color>\w+)").unwrap());
 
 impl Hand {
+    fn new<'a, T: IntoIterator<Item = (&'a str, i32)>>(cubes: T) -> Hand {
+        let mut hand = Hand::default();
+        for (color, count) in cubes {
+            hand.set(color, count);
+        }
+        hand
+    }
+
     fn from(inp: &str) -> Hand {
-        let fail_msg = format!("{} is not a valid hand", inp);
+        Hand::parse(inp, ParseMode::Lenient)
+            .unwrap_or_else(|_| panic!("{} is not a valid hand", inp))
+    }
 
-        let mut set = Hand {
-            red: 0,
-            blue: 0,
-            green: 0,
+    fn parse(inp: &str, mode: ParseMode) -> Result<Hand, ParseError> {
+        Self::parse_at(inp, 0, mode)
+    }
+
+    /// Parses a draw found at `offset` in its line, so errors point at the
+    /// position in the whole line
+    fn parse_at(inp: &str, offset: usize, mode: ParseMode) -> Result<Hand, ParseError> {
+        let error = |position: usize, kind| ParseError {
+            position: offset + position,
+            kind,
+        };
+        // Anything other than whitespace around the separators is garbage
+        let check_gap = |start: usize, end: usize, separated: bool| {
+            let gap = &inp[start..end];
+            let valid = if separated {
+                SEPARATOR_REGEX.is_match(gap)
+            } else {
+                gap.trim().is_empty()
+            };
+            if valid || mode == ParseMode::Lenient {
+                Ok(())
+            } else {
+                let text = gap.trim();
+                let position = start + gap.find(text).unwrap_or(0);
+                Err(error(position, ParseErrorKind::UnexpectedText(text.into())))
+            }
         };
+
+        let mut set = Hand::default();
+        let mut seen = BTreeSet::new();
+        let mut last_end = None;
         for cap in HAND_REGEX.captures_iter(inp) {
-            let value = cap
-                .name("val")
-                .expect(&fail_msg)
-                .as_str()
-                .parse::<i32>()
-                .expect(&fail_msg);
-            let color = cap.name("color").expect(&fail_msg).as_str();
-
-            match color {
-                "red" => set.red = value,
-                "blue" => set.blue = value,
-                "green" => set.green = value,
-                _ => panic!("Unknown color"),
+            let whole = cap.get(0).unwrap();
+            check_gap(last_end.unwrap_or(0), whole.start(), last_end.is_some())?;
+            last_end = Some(whole.end());
+
+            let val = cap.name("val").unwrap();
+            let value = val.as_str().parse::<i32>().map_err(|_| {
+                error(
+                    val.start(),
+                    ParseErrorKind::InvalidCount(val.as_str().into()),
+                )
+            })?;
+
+            // Zero counts aren't stored in the hand, so track colours separately
+            let color = cap.name("color").unwrap();
+            if !seen.insert(color.as_str()) && mode == ParseMode::Strict {
+                let kind = ParseErrorKind::DuplicateColor(color.as_str().into());
+                return Err(error(color.start(), kind));
+            }
+            set.set(color.as_str(), set.get(color.as_str()) + value);
+        }
+
+        match last_end {
+            Some(end) => check_gap(end, inp.len(), false)?,
+            // A draw with text but no counts is garbage rather than empty
+            None if mode == ParseMode::Strict && !inp.trim().is_empty() => {
+                check_gap(0, inp.len(), false)?;
+            }
+            None if mode == ParseMode::Strict => {
+                return Err(error(
+                    inp.len() - inp.trim_start().len(),
+                    ParseErrorKind::EmptyDraw,
+                ));
+            }
+            None => {}
+        }
+
+        Ok(set)
+    }
+
+    fn get(&self, color: &str) -> i32 {
+        self.cubes.get(color).copied().unwrap_or(0)
+    }
+
+    /// Zero counts aren't stored so that hands with and without an empty
+    /// colour are equal
+    fn set(&mut self, color: &str, count: i32) {
+        if count == 0 {
+            self.cubes.remove(color);
+        } else {
+            self.cubes.insert(color.to_string(), count);
+        }
+    }
+
+    fn colors(&self) -> impl Iterator<Item = &str> {
+        self.cubes.keys().map(String::as_str)
+    }
+
+    fn colors_and_counts(&self) -> impl Iterator<Item = (&str, i32)> {
+        self.cubes
+            .iter()
+            .map(|(color, count)| (color.as_str(), *count))
+    }
+
+    /// The total number of cubes
+    fn total(&self) -> i32 {
+        self.cubes.values().sum()
+    }
+
+    /// The natural log of the probability of drawing exactly this hand from
+    /// the bag, which is negative infinity if it can't be drawn
+    fn draw_log_probability(&self, bag: &Hand, replacement: Replacement) -> f64 {
+        let drawn = self.total();
+        let size = bag.total();
+        if size == 0 {
+            return if drawn == 0 { 0.0 } else { f64::NEG_INFINITY };
+        }
+
+        match replacement {
+            // Multinomial: each cube is drawn independently
+            Replacement::With => {
+                let orderings = ln_factorial(drawn)
+                    - self
+                        .colors_and_counts()
+                        .map(|(_, count)| ln_factorial(count))
+                        .sum::<f64>();
+                let chances = self
+                    .colors_and_counts()
+                    .map(|(color, count)| count as f64 * (bag.get(color) as f64 / size as f64).ln())
+                    .sum::<f64>();
+                orderings + chances
+            }
+            // Multivariate hypergeometric: the handful is taken all at once
+            Replacement::Without => {
+                if self
+                    .colors_and_counts()
+                    .any(|(color, count)| count > bag.get(color))
+                {
+                    return f64::NEG_INFINITY;
+                }
+                bag.colors_and_counts()
+                    .map(|(color, count)| ln_choose(count, self.get(color)))
+                    .sum::<f64>()
+                    - ln_choose(size, drawn)
             }
         }
-        set
     }
 
-    fn power(&self) -> i32 {
-        self.red * self.blue * self.green
+    /// The product of the counts of the given colours, which is zero if any
+    /// of them is missing from the hand
+    fn power_over<'a>(&self, colors: impl IntoIterator<Item = &'a str>) -> i32 {
+        colors.into_iter().map(|color| self.get(color)).product()
     }
 }
 
+impl Display for Hand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cubes = self
+            .colors_and_counts()
+            .map(|(color, count)| format!("{} {}", count, color))
+            .collect::<Vec<_>>();
+        write!(f, "{}", cubes.join(", "))
+    }
+}
+
+impl From<BTreeMap<String, i32>> for Hand {
+    fn from(cubes: BTreeMap<String, i32>) -> Hand {
+        let mut hand = Hand::default();
+        for (color, count) in cubes {
+            hand.set(&color, count);
+        }
+        hand
+    }
+}
+
+impl From<Hand> for BTreeMap<String, i32> {
+    fn from(hand: Hand) -> BTreeMap<String, i32> {
+        hand.cubes
+    }
+}
+
+#[derive(PartialEq, Debug)]
 struct Game {
     id: i32,
     hands: Vec<Hand>,
 }
 
+/// The draw that forces the minimum number of cubes of a colour in a game
+#[derive(PartialEq, Debug)]
+struct Constraint {
+    color: String,
+    count: i32,
+    /// The index of the first draw with that many cubes
+    draw: usize,
+}
+
 static GAME_REGEX: Lazy<Regex> =
     Lazy::new(|| Regex::new(r"Game (?<id>\d+): (?<hands>.*)").unwrap());
 
-impl Game {
+impl From<&str> for Game {
     fn from(inp: &str) -> Game {
-        let fail_msg = format!("{} is not a valid game", inp);
+        Game::parse(inp, ParseMode::Lenient)
+            .unwrap_or_else(|_| panic!("{} is not a valid game", inp))
+    }
+}
+
+impl Game {
+    fn parse(inp: &str, mode: ParseMode) -> Result<Game, ParseError> {
+        let missing_id = ParseError {
+            position: 0,
+            kind: ParseErrorKind::MissingGameId,
+        };
+        let captures = GAME_REGEX.captures(inp).ok_or(missing_id)?;
+
+        let start = captures.get(0).unwrap().start();
+        if mode == ParseMode::Strict && start > 0 {
+            return Err(ParseError {
+                position: 0,
+                kind: ParseErrorKind::UnexpectedText(inp[..start].into()),
+            });
+        }
 
-        let captures = GAME_REGEX.captures(inp).expect(&fail_msg);
-        let id = captures
-            .name("id")
-            .expect(&fail_msg)
-            .as_str()
-            .parse::<i32>()
-            .expect(&fail_msg);
+        let id_match = captures.name("id").unwrap();
+        let id = id_match.as_str().parse::<i32>().map_err(|_| ParseError {
+            position: id_match.start(),
+            kind: ParseErrorKind::InvalidCount(id_match.as_str().into()),
+        })?;
 
-        let hands = captures
-            .name("hands")
-            .expect(&fail_msg)
-            .as_str()
-            .split(";")
-            .map(Hand::from)
-            .collect();
+        let hands_match = captures.name("hands").unwrap();
+        let mut hands = vec![];
+        let mut offset = hands_match.start();
+        for draw in hands_match.as_str().split(';') {
+            hands.push(Hand::parse_at(draw, offset, mode)?);
+            offset += draw.len() + 1;
+        }
 
-        Game { id, hands }
+        Ok(Game { id, hands })
     }
 
+    /// Whether every hand could be drawn from a bag of `max_hand`. Colours
+    /// that aren't in the bag can't be drawn.
     fn is_valid(&self, max_hand: &Hand) -> bool {
+        self.hands.iter().all(|hand| {
+            hand.colors()
+                .all(|color| hand.get(color) <= max_hand.get(color))
+        })
+    }
+
+    fn min_val_hand(&self) -> Hand {
+        let mut min_hand = Hand::default();
+
         for hand in &self.hands {
-            if hand.red > max_hand.red || hand.blue > max_hand.blue || hand.green > max_hand.green {
-                return false;
+            for color in hand.colors() {
+                if hand.get(color) > min_hand.get(color) {
+                    min_hand.set(color, hand.get(color));
+                }
             }
         }
-        true
+
+        min_hand
     }
 
-    fn min_val_hand(&self) -> Hand {
-        let mut min_hand = Hand {
-            red: 0,
-            blue: 0,
-            green: 0,
-        };
+    /// For every colour drawn, the first draw with the most cubes of that
+    /// colour, which is what sets its count in `min_val_hand`
+    fn tightest_constraints(&self) -> Vec<Constraint> {
+        let mut constraints: BTreeMap<&str, Constraint> = BTreeMap::new();
 
-        for hand in &self.hands {
-            if hand.red > min_hand.red {
-                min_hand.red = hand.red;
+        for (draw, hand) in self.hands.iter().enumerate() {
+            for (color, count) in hand.colors_and_counts() {
+                let tighter = constraints.get(color).is_none_or(|c| count > c.count);
+                if tighter {
+                    let constraint = Constraint {
+                        color: color.to_string(),
+                        count,
+                        draw,
+                    };
+                    constraints.insert(color, constraint);
+                }
             }
-            if hand.blue > min_hand.blue {
-                min_hand.blue = hand.blue;
+        }
+
+        constraints.into_values().collect()
+    }
+}
+
+/// A game along with the figures derived from it, for analysis outside this
+/// program. Only `id` and `draws` are read back; the rest depend on the bag
+/// and are recomputed, so fixtures can leave them out.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct GameRecord {
+    id: i32,
+    draws: Vec<Hand>,
+    #[serde(default)]
+    minimal_bag: Hand,
+    #[serde(default)]
+    power: i32,
+    #[serde(default)]
+    valid: bool,
+}
+
+impl GameRecord {
+    fn new(game: &Game, bag: &Hand) -> GameRecord {
+        let minimal_bag = game.min_val_hand();
+        GameRecord {
+            id: game.id,
+            draws: game.hands.clone(),
+            power: minimal_bag.power_over(bag.colors()),
+            minimal_bag,
+            valid: game.is_valid(bag),
+        }
+    }
+}
+
+impl From<GameRecord> for Game {
+    fn from(record: GameRecord) -> Game {
+        Game {
+            id: record.id,
+            hands: record.draws,
+        }
+    }
+}
+
+/// CSV can't nest, so the draws and minimal bag are written the way the
+/// puzzle writes them, e.g. `3 blue, 4 red; 1 red, 2 green`.
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+struct CsvRecord {
+    id: i32,
+    draws: String,
+    minimal_bag: String,
+    power: i32,
+    valid: bool,
+}
+
+impl From<GameRecord> for CsvRecord {
+    fn from(record: GameRecord) -> CsvRecord {
+        let draws = record.draws.iter().map(Hand::to_string).collect::<Vec<_>>();
+        CsvRecord {
+            id: record.id,
+            draws: draws.join("; "),
+            minimal_bag: record.minimal_bag.to_string(),
+            power: record.power,
+            valid: record.valid,
+        }
+    }
+}
+
+impl TryFrom<CsvRecord> for Game {
+    type Error = ParseError;
+
+    fn try_from(record: CsvRecord) -> Result<Game, ParseError> {
+        Game::parse(
+            &format!("Game {}: {}", record.id, record.draws),
+            ParseMode::Lenient,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RecordFormat {
+    Json,
+    Csv,
+}
+
+impl FromStr for RecordFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(RecordFormat::Json),
+            "csv" => Ok(RecordFormat::Csv),
+            _ => Err(format!("Unknown record format {}", s)),
+        }
+    }
+}
+
+fn export_games(
+    games: &[Game],
+    bag: &Hand,
+    format: RecordFormat,
+) -> Result<String, Box<dyn Error>> {
+    let records = games.iter().map(|game| GameRecord::new(game, bag));
+    match format {
+        RecordFormat::Json => {
+            let mut json = serde_json::to_string_pretty(&records.collect::<Vec<_>>())?;
+            json.push('\n');
+            Ok(json)
+        }
+        RecordFormat::Csv => {
+            let mut writer = csv::Writer::from_writer(vec![]);
+            for record in records {
+                writer.serialize(CsvRecord::from(record))?;
             }
-            if hand.green > min_hand.green {
-                min_hand.green = hand.green;
+            Ok(String::from_utf8(writer.into_inner()?)?)
+        }
+    }
+}
+
+fn read_games(inp: &str, format: RecordFormat) -> Result<Vec<Game>, Box<dyn Error>> {
+    match format {
+        RecordFormat::Json => {
+            let records: Vec<GameRecord> = serde_json::from_str(inp)?;
+            Ok(records.into_iter().map(Game::from).collect())
+        }
+        RecordFormat::Csv => {
+            let mut reader = csv::Reader::from_reader(inp.as_bytes());
+            let mut games = vec![];
+            for record in reader.deserialize::<CsvRecord>() {
+                games.push(Game::try_from(record?)?);
             }
+            Ok(games)
         }
+    }
+}
 
-        min_hand
+/// Reads games written by `--export`, picking the format from the extension
+fn import_games(path: &str) -> Result<Vec<Game>, Box<dyn Error>> {
+    let format = match path.rsplit_once('.') {
+        Some((_, extension)) => extension.parse::<RecordFormat>()?,
+        None => return Err(format!("{} has no extension", path).into()),
+    };
+    read_games(&std::fs::read_to_string(path)?, format)
+}
+
+/// How cubes are drawn from the bag. Either way, every draw is put back
+/// before the next one, as in the puzzle.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Replacement {
+    /// Each cube is put back before the next cube is taken
+    With,
+    /// A draw is a single handful of distinct cubes
+    Without,
+}
+
+/// `ln(n!)` for small `n`, since the same factorials are needed for every draw
+static LN_FACTORIALS: Lazy<Vec<f64>> = Lazy::new(|| {
+    let mut table = vec![0.0];
+    for i in 1..1000 {
+        table.push(table[i - 1] + (i as f64).ln());
+    }
+    table
+});
+
+fn ln_factorial(n: i32) -> f64 {
+    match LN_FACTORIALS.get(n as usize) {
+        Some(&value) => value,
+        None => (2..=n).map(|i| (i as f64).ln()).sum(),
+    }
+}
+
+fn ln_choose(n: i32, k: i32) -> f64 {
+    if k < 0 || k > n {
+        f64::NEG_INFINITY
+    } else {
+        ln_factorial(n) - ln_factorial(k) - ln_factorial(n - k)
+    }
+}
+
+impl Game {
+    fn log_probability(&self, bag: &Hand, replacement: Replacement) -> f64 {
+        self.hands
+            .iter()
+            .map(|hand| hand.draw_log_probability(bag, replacement))
+            .sum()
+    }
+
+    /// The probability of seeing this game's sequence of draws from the bag
+    fn probability(&self, bag: &Hand, replacement: Replacement) -> f64 {
+        self.log_probability(bag, replacement).exp()
     }
 }
 
+/// The maximum-likelihood bag of `size` cubes for the games, or `None` if no
+/// bag of that size can produce them.
+///
+/// Every way of splitting `size` between the colours seen is tried, so this
+/// grows as `size` to the power of one less than the number of colours.
+fn most_likely_bag(games: &[Game], size: i32, replacement: Replacement) -> Option<Hand> {
+    let colors = minimal_bag(games)
+        .colors()
+        .map(String::from)
+        .collect::<Vec<_>>();
+
+    let mut best: Option<(f64, Hand)> = None;
+    for counts in compositions(size, colors.len()) {
+        let bag = Hand::new(colors.iter().map(String::as_str).zip(counts));
+        let likelihood = games
+            .iter()
+            .map(|game| game.log_probability(&bag, replacement))
+            .sum::<f64>();
+
+        let better = best.as_ref().is_none_or(|(b, _)| likelihood > *b);
+        if likelihood > f64::NEG_INFINITY && better {
+            best = Some((likelihood, bag));
+        }
+    }
+
+    best.map(|(_, bag)| bag)
+}
+
+/// Every way of splitting `total` into `parts` counts
+fn compositions(total: i32, parts: usize) -> Vec<Vec<i32>> {
+    if parts == 0 {
+        return if total == 0 { vec![vec![]] } else { vec![] };
+    }
+
+    (0..=total)
+        .flat_map(|first| {
+            compositions(total - first, parts - 1)
+                .into_iter()
+                .map(move |mut rest| {
+                    rest.insert(0, first);
+                    rest
+                })
+        })
+        .collect()
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -155,7 +748,7 @@ mod test {
         lines
             .map(|line| {
                 let game = Game::from(line.as_ref());
-                game.min_val_hand().power()
+                game.min_val_hand().power_over(["red", "green", "blue"])
             })
             .collect()
     }
@@ -172,21 +765,9 @@ mod test {
         assert_eq!(
             result.hands,
             vec![
-                Hand {
-                    red: 4,
-                    blue: 3,
-                    green: 0
-                },
-                Hand {
-                    red: 1,
-                    blue: 6,
-                    green: 2
-                },
-                Hand {
-                    red: 0,
-                    blue: 0,
-                    green: 2
-                }
+                Hand::new([("red", 4), ("blue", 3), ("green", 0)]),
+                Hand::new([("red", 1), ("blue", 6), ("green", 2)]),
+                Hand::new([("red", 0), ("blue", 0), ("green", 2)])
             ]
         );
     }
@@ -194,14 +775,7 @@ mod test {
     #[test]
     fn test_parsing_set() {
         let result = Hand::from("3 blue, 4 red");
-        assert_eq!(
-            result,
-            Hand {
-                red: 4,
-                blue: 3,
-                green: 0
-            }
-        );
+        assert_eq!(result, Hand::new([("red", 4), ("blue", 3), ("green", 0)]));
     }
 
     const TEST_INPUT: &str = "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
@@ -212,11 +786,7 @@ mod test {
 
     #[test]
     fn test_valid_games() {
-        let max_val_hand = Hand {
-            red: 12,
-            blue: 14,
-            green: 13,
-        };
+        let max_val_hand = Hand::new([("red", 12), ("blue", 14), ("green", 13)]);
 
         let result = valid_games(TEST_INPUT.lines(), &max_val_hand);
         assert_eq!(vec![1, 2, 5], result);
@@ -226,30 +796,397 @@ mod test {
     fn test_lowest_hand() {
         let game = Game::from("Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green");
         let result = game.min_val_hand();
-        assert_eq!(
-            result,
-            Hand {
-                red: 4,
-                blue: 6,
-                green: 2
-            }
-        );
+        assert_eq!(result, Hand::new([("red", 4), ("blue", 6), ("green", 2)]));
     }
 
     #[test]
     fn test_power_of_hand() {
-        let hand = Hand {
-            red: 4,
-            blue: 6,
-            green: 2,
-        };
-        let result = hand.power();
+        let hand = Hand::new([("red", 4), ("blue", 6), ("green", 2)]);
+        let result = hand.power_over(["red", "green", "blue"]);
         assert_eq!(result, 48);
     }
 
+    #[test]
+    fn test_power_of_missing_colour() {
+        let game = Game::from("Game 1: 3 red; 2 green");
+        assert_eq!(game.min_val_hand().power_over(["red", "green", "blue"]), 0);
+        assert_eq!(game.min_val_hand().power_over(["red", "green"]), 6);
+    }
+
+    #[test]
+    fn test_parsing_any_color() {
+        let game = Game::from("Game 7: 2 purple, 1 red; 3 purple, 4 orange");
+        assert_eq!(
+            game.min_val_hand(),
+            Hand::new([("purple", 3), ("red", 1), ("orange", 4)])
+        );
+        assert_eq!(
+            game.min_val_hand().power_over(["purple", "red", "orange"]),
+            12
+        );
+
+        let bag = Hand::new([("red", 12), ("blue", 14), ("green", 13)]);
+        assert!(
+            !game.is_valid(&bag),
+            "Colours missing from the bag can't be drawn"
+        );
+
+        let bag = Hand::new([("red", 1), ("purple", 3), ("orange", 4)]);
+        assert!(game.is_valid(&bag));
+    }
+
+    #[test]
+    fn test_zero_counts_are_ignored() {
+        assert_eq!(Hand::from("0 red, 2 blue"), Hand::new([("blue", 2)]));
+        assert_eq!(Hand::new([]).power_over(["red"]), 0);
+    }
+
     #[test]
     fn test_powers() {
         let result = powers(TEST_INPUT.lines());
         assert_eq!(vec![48, 12, 1560, 630, 36], result);
     }
+
+    fn test_games() -> Vec<Game> {
+        TEST_INPUT.lines().map(Game::from).collect()
+    }
+
+    #[test]
+    fn test_minimal_bag() {
+        assert_eq!(
+            minimal_bag(&test_games()),
+            Hand::new([("red", 20), ("blue", 15), ("green", 13)])
+        );
+    }
+
+    #[test]
+    fn test_feasible_games() {
+        let games = test_games();
+        let bag = Hand::new([("red", 12), ("blue", 14), ("green", 13)]);
+        assert_eq!(feasible_games(&games, &bag), BTreeSet::from([1, 2, 5]));
+
+        let bag = minimal_bag(&games);
+        assert_eq!(
+            feasible_games(&games, &bag),
+            BTreeSet::from([1, 2, 3, 4, 5])
+        );
+    }
+
+    #[test]
+    fn test_tightest_constraints() {
+        let game =
+            Game::from("Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red");
+        assert_eq!(
+            game.tightest_constraints(),
+            vec![
+                Constraint {
+                    color: "blue".into(),
+                    count: 15,
+                    draw: 2
+                },
+                Constraint {
+                    color: "green".into(),
+                    count: 3,
+                    draw: 1
+                },
+                Constraint {
+                    color: "red".into(),
+                    count: 14,
+                    draw: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bag_report() {
+        let games = test_games();
+        let bag = Hand::new([("red", 12), ("blue", 14), ("green", 13)]);
+        let report = bag_report(&games, &bag);
+        let lines = report.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], "Minimal bag: 15 blue, 13 green, 20 red");
+        assert_eq!(
+            lines[1],
+            "Possible with 14 blue, 13 green, 12 red: 3 of 5 games {1, 2, 5}"
+        );
+        assert_eq!(
+            lines[2],
+            "Game 1: 6 blue (draw 2), 2 green (draw 2), 4 red (draw 1)"
+        );
+    }
+
+    #[test]
+    fn test_lenient_parsing_sums_duplicates() {
+        assert_eq!(Hand::from("3 red, 4 red"), Hand::new([("red", 7)]));
+        assert_eq!(
+            Hand::parse("3 red and 4 blue", ParseMode::Lenient),
+            Ok(Hand::new([("red", 3), ("blue", 4)]))
+        );
+    }
+
+    #[test]
+    fn test_strict_parsing() {
+        let tests = [
+            (
+                "Game 1: 3 red, 4 red",
+                ParseError {
+                    position: 17,
+                    kind: ParseErrorKind::DuplicateColor("red".into()),
+                },
+            ),
+            (
+                "Game 1: 0 red, 4 red",
+                ParseError {
+                    position: 17,
+                    kind: ParseErrorKind::DuplicateColor("red".into()),
+                },
+            ),
+            (
+                "Game 1: 3 red and 4 blue",
+                ParseError {
+                    position: 14,
+                    kind: ParseErrorKind::UnexpectedText("and".into()),
+                },
+            ),
+            (
+                "Game 1: 3 red, 4 blue;; 1 green",
+                ParseError {
+                    position: 22,
+                    kind: ParseErrorKind::EmptyDraw,
+                },
+            ),
+            (
+                "Game 1: 3 red; blue",
+                ParseError {
+                    position: 15,
+                    kind: ParseErrorKind::UnexpectedText("blue".into()),
+                },
+            ),
+            (
+                "Game 1: 3 red; 3red",
+                ParseError {
+                    position: 15,
+                    kind: ParseErrorKind::UnexpectedText("3red".into()),
+                },
+            ),
+            (
+                "Game 1: 3 red; 2 blue!",
+                ParseError {
+                    position: 21,
+                    kind: ParseErrorKind::UnexpectedText("!".into()),
+                },
+            ),
+            (
+                "Game 1: 3 red,, 2 blue",
+                ParseError {
+                    position: 13,
+                    kind: ParseErrorKind::UnexpectedText(",,".into()),
+                },
+            ),
+            (
+                "Game 1: 99999999999 red",
+                ParseError {
+                    position: 8,
+                    kind: ParseErrorKind::InvalidCount("99999999999".into()),
+                },
+            ),
+            (
+                "junk Game 1: 3 red",
+                ParseError {
+                    position: 0,
+                    kind: ParseErrorKind::UnexpectedText("junk ".into()),
+                },
+            ),
+            (
+                "Round 1: 3 red",
+                ParseError {
+                    position: 0,
+                    kind: ParseErrorKind::MissingGameId,
+                },
+            ),
+        ];
+
+        for (line, expected) in tests {
+            assert_eq!(
+                Game::parse(line, ParseMode::Strict).err(),
+                Some(expected),
+                "{}",
+                line
+            );
+        }
+
+        for line in TEST_INPUT.lines() {
+            assert!(
+                Game::parse(line.trim(), ParseMode::Strict).is_ok(),
+                "{}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_error_display() {
+        let err = Game::parse("Game 1: 3 red, 4 red", ParseMode::Strict).unwrap_err();
+        assert_eq!(err.to_string(), "byte 17: red appears more than once");
+
+        let err = Game::parse("Game 1: 3 rød, 4 rød", ParseMode::Strict).unwrap_err();
+        assert_eq!(err.to_string(), "byte 18: rød appears more than once");
+    }
+
+    fn assert_close(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() < 1e-9,
+            "{} should be {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn test_draw_probability() {
+        let bag = Hand::new([("red", 2), ("blue", 1)]);
+
+        let one_red = Hand::from("1 red");
+        assert_close(
+            one_red.draw_log_probability(&bag, Replacement::With).exp(),
+            2.0 / 3.0,
+        );
+        assert_close(
+            one_red
+                .draw_log_probability(&bag, Replacement::Without)
+                .exp(),
+            2.0 / 3.0,
+        );
+
+        let two_red = Hand::from("2 red");
+        assert_close(
+            two_red.draw_log_probability(&bag, Replacement::With).exp(),
+            4.0 / 9.0,
+        );
+        assert_close(
+            two_red
+                .draw_log_probability(&bag, Replacement::Without)
+                .exp(),
+            1.0 / 3.0,
+        );
+
+        let mixed = Hand::from("1 red, 1 blue");
+        assert_close(
+            mixed.draw_log_probability(&bag, Replacement::With).exp(),
+            4.0 / 9.0,
+        );
+        assert_close(
+            mixed.draw_log_probability(&bag, Replacement::Without).exp(),
+            2.0 / 3.0,
+        );
+
+        let three_red = Hand::from("3 red");
+        assert_close(
+            three_red
+                .draw_log_probability(&bag, Replacement::With)
+                .exp(),
+            8.0 / 27.0,
+        );
+        assert_close(
+            three_red
+                .draw_log_probability(&bag, Replacement::Without)
+                .exp(),
+            0.0,
+        );
+
+        let green = Hand::from("1 green");
+        assert_close(
+            green.draw_log_probability(&bag, Replacement::With).exp(),
+            0.0,
+        );
+        assert_close(
+            green.draw_log_probability(&bag, Replacement::Without).exp(),
+            0.0,
+        );
+    }
+
+    #[test]
+    fn test_game_probability() {
+        let bag = Hand::new([("red", 2), ("blue", 1)]);
+        let game = Game::from("Game 1: 1 red; 1 blue");
+        assert_close(game.probability(&bag, Replacement::With), 2.0 / 9.0);
+        assert_close(game.probability(&bag, Replacement::Without), 2.0 / 9.0);
+    }
+
+    #[test]
+    fn test_most_likely_bag() {
+        let games = [
+            Game::from("Game 1: 2 red; 1 red, 1 blue"),
+            Game::from("Game 2: 1 red; 2 red"),
+        ];
+
+        assert_eq!(
+            most_likely_bag(&games, 3, Replacement::Without),
+            Some(Hand::new([("red", 2), ("blue", 1)]))
+        );
+        assert_eq!(
+            most_likely_bag(&games, 7, Replacement::With),
+            Some(Hand::new([("red", 6), ("blue", 1)]))
+        );
+        assert_eq!(most_likely_bag(&games, 1, Replacement::Without), None);
+    }
+
+    #[test]
+    fn test_json_records() {
+        let bag = Hand::new([("red", 12), ("blue", 14), ("green", 13)]);
+        let games = vec![Game::from("Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red")];
+        let json = export_games(&games, &bag, RecordFormat::Json).unwrap();
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(
+            value,
+            serde_json::json!([{
+                "id": 3,
+                "draws": [
+                    {"blue": 6, "green": 8, "red": 20},
+                    {"blue": 5, "red": 4}
+                ],
+                "minimal_bag": {"blue": 6, "green": 8, "red": 20},
+                "power": 960,
+                "valid": false
+            }])
+        );
+    }
+
+    #[test]
+    fn test_csv_records() {
+        let bag = Hand::new([("red", 12), ("blue", 14), ("green", 13)]);
+        let games = vec![Game::from(
+            "Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green",
+        )];
+
+        assert_eq!(
+            export_games(&games, &bag, RecordFormat::Csv).unwrap(),
+            "id,draws,minimal_bag,power,valid\n\
+             1,\"3 blue, 4 red; 6 blue, 2 green, 1 red; 2 green\",\"6 blue, 2 green, 4 red\",48,true\n"
+        );
+    }
+
+    #[test]
+    fn test_records_round_trip() {
+        let bag = Hand::new([("red", 12), ("blue", 14), ("green", 13)]);
+        for format in [RecordFormat::Json, RecordFormat::Csv] {
+            let exported = export_games(&test_games(), &bag, format).unwrap();
+            assert_eq!(read_games(&exported, format).unwrap(), test_games());
+        }
+    }
+
+    #[test]
+    fn test_reading_records_drops_zero_counts() {
+        let json = r#"[{"id": 1, "draws": [{"red": 0, "blue": 2}]}]"#;
+        let games = read_games(json, RecordFormat::Json).unwrap();
+        assert_eq!(games[0].hands, vec![Hand::new([("blue", 2)])]);
+    }
+
+    #[test]
+    fn test_reading_invalid_csv_draws() {
+        let csv = "id,draws,minimal_bag,power,valid\none,3 red,3 red,3,true\n";
+        assert!(read_games(csv, RecordFormat::Csv).is_err());
+    }
 }
@@ -1,11 +1,119 @@
-use std::cmp::max;
+use std::{
+    cmp::max,
+    collections::BTreeSet,
+    fmt::Display,
+    ops::{Range, RangeInclusive},
+    str::FromStr,
+    time::Instant,
+};
 
-use advent_of_code_2023::io::read_lines;
+use advent_of_code_2023::{io::read_lines, random::XorShift};
 use once_cell::sync::Lazy;
 use regex::Regex;
 
 fn main() {
-    let schematic = Schematic::parse(read_lines("inputs/day_3.txt"));
+    let mut bench_iterations = None;
+    let mut rule = GearRule::default();
+    let mut render = None;
+    let mut components = false;
+    let mut dialect = Dialect::default();
+    let mut edits = vec![];
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bench" => {
+                let iterations = args
+                    .next()
+                    .expect("--bench requires a number of iterations");
+                bench_iterations = Some(iterations.parse::<u32>().unwrap());
+            }
+            "--gear-symbols" => {
+                let symbols = args.next().expect("--gear-symbols requires symbols");
+                rule.symbols = symbols.chars().collect();
+            }
+            "--gear-parts" => {
+                let count = args.next().expect("--gear-parts requires a count or range");
+                rule.parts = parse_count_range(&count).unwrap();
+            }
+            "--gear-ratio" => {
+                let ratio = args.next().expect("--gear-ratio requires product or sum");
+                rule.ratio = match ratio.as_str() {
+                    "product" => product_ratio,
+                    "sum" => sum_ratio,
+                    _ => panic!("Unknown gear ratio {}", ratio),
+                };
+            }
+            "--background" => {
+                let background = args.next().expect("--background requires characters");
+                dialect.background = background.chars().collect();
+            }
+            "--signed" => dialect.signed_numbers = true,
+            "--symbols" => {
+                let symbols = args
+                    .next()
+                    .expect("--symbols requires any, punctuation or characters");
+                dialect.symbols = symbols.parse::<SymbolClass>().unwrap();
+            }
+            "--components" => components = true,
+            "--set" => {
+                let cell = args.next().expect("--set requires row,column,character");
+                match parse_cell(&cell).unwrap() {
+                    (row, column, Some(c)) => edits.push(Edit::Set { row, column, c }),
+                    _ => panic!("--set requires a character"),
+                }
+            }
+            "--insert-symbol" => {
+                let cell = args
+                    .next()
+                    .expect("--insert-symbol requires row,column,symbol");
+                match parse_cell(&cell).unwrap() {
+                    (row, column, Some(symbol)) => edits.push(Edit::InsertSymbol {
+                        row,
+                        column,
+                        symbol,
+                    }),
+                    _ => panic!("--insert-symbol requires a symbol"),
+                }
+            }
+            "--remove-symbol" => {
+                let cell = args.next().expect("--remove-symbol requires row,column");
+                let (row, column, _) = parse_cell(&cell).unwrap();
+                edits.push(Edit::RemoveSymbol { row, column });
+            }
+            "--render" => {
+                let mode = args.next().expect("--render requires ansi, plain or html");
+                render = Some(mode.parse::<RenderMode>().unwrap());
+            }
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+
+    if let Some(iterations) = bench_iterations {
+        benchmark(&generate_schematic(1000, 1000, 0x2023), &rule, iterations);
+        return;
+    }
+
+    let lines = read_lines("inputs/day_3.txt");
+    let mut schematic = Schematic::parse_with(lines, &dialect).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    for edit in edits {
+        if let Err(err) = schematic.apply(edit) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+
+    if let Some(mode) = render {
+        print!("{}", schematic.render(&rule, mode));
+        return;
+    }
+
+    if components {
+        println!("{}", schematic.component_summary());
+    }
 
     {
         let result = schematic
@@ -16,110 +124,683 @@ fn main() {
         println!("Day 3, Star 1: {}", result);
     }
 
-    let result = schematic.gears().iter().map(|g| g.ratio).sum::<i32>();
+    let result = schematic.gears(&rule).iter().map(|g| g.ratio).sum::<i32>();
     println!("Day 3, Star 2: {}", result);
 }
 
+/// Times finding valid parts and gears by scanning every part against every
+/// symbol and by looking up neighbouring cells in the grid index
+fn benchmark<T: AsRef<str>>(lines: &[T], rule: &GearRule, iterations: u32) {
+    let start = Instant::now();
+    let schematic = Schematic::parse(lines.iter()).unwrap();
+    println!("{:<6} {:>12.2?}", "parse", start.elapsed());
+
+    time("scan", iterations, || {
+        (
+            schematic.valid_parts_by_scan(),
+            schematic.gears_by_scan(rule),
+        )
+    });
+    time("index", iterations, || {
+        (schematic.valid_parts(), schematic.gears(rule))
+    });
+}
+
+fn time<F: Fn() -> (Vec<Part>, Vec<Gear>)>(name: &str, iterations: u32, find: F) {
+    let start = Instant::now();
+    let mut totals = (0, 0);
+    for _ in 0..iterations {
+        let (parts, gears) = find();
+        totals = (
+            parts.iter().map(|p| p.number).sum::<i32>(),
+            gears.iter().map(|g| g.ratio).sum::<i32>(),
+        );
+    }
+    let elapsed = start.elapsed() / iterations.max(1);
+    println!(
+        "{:<6} {:>12.2?} per pass, totals {:?}",
+        name, elapsed, totals
+    );
+}
+
+/// A reproducible schematic of random numbers and symbols on a background
+/// of dots
+fn generate_schematic(width: usize, height: usize, seed: u64) -> Vec<String> {
+    // A fixed seed keeps the benchmark comparable between runs
+    let mut rng = XorShift::new(seed);
+
+    (0..height)
+        .map(|_| {
+            let mut line = String::with_capacity(width);
+            while line.len() < width {
+                match rng.below(16) {
+                    0 => {
+                        let digits = rng.below(3) + 1;
+                        for _ in 0..digits.min(width - line.len()) {
+                            line.push((b'0' + rng.below(10) as u8) as char);
+                        }
+                        if line.len() < width {
+                            line.push('.');
+                        }
+                    }
+                    1 => line.push(['*', '#', '+', '$', '/', '=', '%', '@'][rng.below(8)]),
+                    _ => line.push('.'),
+                }
+            }
+            line
+        })
+        .collect()
+}
+
+/// A row whose width differs from the first row's
+#[derive(Debug, PartialEq)]
+struct RaggedRow {
+    /// Counting from 1
+    line: usize,
+    width: usize,
+}
+
+#[derive(Debug, PartialEq)]
+enum SchematicError {
+    Ragged {
+        expected: usize,
+        rows: Vec<RaggedRow>,
+    },
+    /// Cells are a byte each, so only ASCII can be laid out on the grid.
+    /// Both counting from 1.
+    NotAscii {
+        line: usize,
+        column: usize,
+        found: char,
+    },
+    /// A part number that doesn't fit in an `i32`, counting from 1
+    NumberTooLarge { line: usize, column: usize },
+}
+
+impl Display for SchematicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SchematicError::Ragged { expected, rows } => {
+                let rows = rows
+                    .iter()
+                    .map(|row| format!("line {} is {} wide", row.line, row.width))
+                    .collect::<Vec<_>>();
+                write!(
+                    f,
+                    "Rows must all be {} wide, but {}",
+                    expected,
+                    rows.join(", ")
+                )
+            }
+            SchematicError::NotAscii {
+                line,
+                column,
+                found,
+            } => write!(
+                f,
+                "Line {}, column {}: {:?} is not ASCII, and cells are one byte wide",
+                line, column, found
+            ),
+            SchematicError::NumberTooLarge { line, column } => {
+                write!(f, "Line {}, column {}: number is too large", line, column)
+            }
+        }
+    }
+}
+
+/// What occupies a cell of the schematic, by index into the parts and symbols
+#[derive(PartialEq, Debug, Copy, Clone)]
+enum Cell {
+    Empty,
+    Part(usize),
+    Symbol(usize),
+}
+
+/// A parsed schematic. Edits keep `raw_parts` and `raw_symbols` in step with
+/// the cells, but not in reading order, so results are sorted by position.
 struct Schematic {
     raw_parts: Vec<Part>,
     raw_symbols: Vec<Symbol>,
+    /// Every cell of the schematic, row by row, so that neighbours can be
+    /// looked up rather than searched for
+    cells: Vec<Cell>,
+    /// The character of every cell as written, so that rendering and edits
+    /// keep signs, leading zeros and characters that aren't symbols
+    text: Vec<u8>,
+    width: usize,
+    /// Whether each part touches a symbol
+    valid: Vec<bool>,
+    /// The ids of the parts around each symbol
+    symbol_parts: Vec<Vec<usize>>,
+    dialect: Dialect,
 }
 
 impl Schematic {
-    pub fn parse<T: Iterator<Item = U>, U: AsRef<str>>(lines: T) -> Self {
+    pub fn parse<T: Iterator<Item = U>, U: AsRef<str>>(lines: T) -> Result<Self, SchematicError> {
+        Self::parse_with(lines, &Dialect::default())
+    }
+
+    /// Parses rows exactly as given, so columns line up with the input. Every
+    /// row must be ASCII and as wide as the first; a trailing `\r` from CRLF
+    /// line endings isn't counted.
+    pub fn parse_with<T: Iterator<Item = U>, U: AsRef<str>>(
+        lines: T,
+        dialect: &Dialect,
+    ) -> Result<Self, SchematicError> {
         let mut raw_parts = Vec::new();
         let mut raw_symbols = Vec::new();
+        let mut text = Vec::new();
+        let mut width = None;
+        let mut height = 0;
+        let mut ragged = vec![];
 
         for (line_no, line) in lines.enumerate() {
-            let (p, s) = Self::parse_line(line_no, line.as_ref().trim());
+            let line = line.as_ref();
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if let Some((column, found)) = line.chars().enumerate().find(|(_, c)| !c.is_ascii()) {
+                return Err(SchematicError::NotAscii {
+                    line: line_no + 1,
+                    column: column + 1,
+                    found,
+                });
+            }
+            let expected = *width.get_or_insert(line.len());
+            if line.len() != expected {
+                ragged.push(RaggedRow {
+                    line: line_no + 1,
+                    width: line.len(),
+                });
+                continue;
+            }
+
+            let (p, s) = Self::parse_line(line_no, line, dialect)?;
             raw_parts.extend(p);
             raw_symbols.extend(s);
+            text.extend_from_slice(line.as_bytes());
+            height = line_no + 1;
+        }
+
+        let width = width.unwrap_or(0);
+        if !ragged.is_empty() {
+            return Err(SchematicError::Ragged {
+                expected: width,
+                rows: ragged,
+            });
         }
 
-        Schematic {
+        let mut cells = vec![Cell::Empty; width * height];
+        for (i, part) in raw_parts.iter().enumerate() {
+            for column in part.start..part.end {
+                cells[part.row * width + column] = Cell::Part(i);
+            }
+        }
+        for (i, symbol) in raw_symbols.iter().enumerate() {
+            cells[symbol.row * width + symbol.column] = Cell::Symbol(i);
+        }
+
+        let mut schematic = Schematic {
+            valid: vec![false; raw_parts.len()],
+            symbol_parts: vec![vec![]; raw_symbols.len()],
             raw_parts,
             raw_symbols,
+            cells,
+            text,
+            width,
+            dialect: dialect.clone(),
+        };
+        schematic.refresh_rows(0..height);
+        Ok(schematic)
+    }
+
+    fn height(&self) -> usize {
+        self.cells.len() / self.width.max(1)
+    }
+
+    /// Works out again which parts are valid and which parts are around each
+    /// symbol, for everything in `rows`
+    fn refresh_rows(&mut self, rows: Range<usize>) {
+        let cells = rows.start * self.width..rows.end * self.width;
+        for i in cells {
+            match self.cells[i] {
+                Cell::Part(id) if self.raw_parts[id].start == i % self.width => {
+                    self.valid[id] = self.is_valid_part(&self.raw_parts[id]);
+                }
+                Cell::Symbol(id) => {
+                    self.symbol_parts[id] = self.adjacent_part_ids(&self.raw_symbols[id]);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn apply(&mut self, edit: Edit) -> Result<(), EditError> {
+        match edit {
+            Edit::Set { row, column, c } => self.set_cell(row, column, c),
+            Edit::InsertSymbol {
+                row,
+                column,
+                symbol,
+            } => self.insert_symbol(row, column, symbol),
+            Edit::RemoveSymbol { row, column } => self.remove_symbol(row, column),
+        }
+    }
+
+    /// Replaces one character, reparsing only its row and updating results
+    /// for the rows next to it
+    pub fn set_cell(&mut self, row: usize, column: usize, c: char) -> Result<(), EditError> {
+        if row >= self.height() || column >= self.width {
+            return Err(EditError::OutOfBounds { row, column });
+        }
+        // Columns are byte offsets, so a wider character would shift the row
+        if !c.is_ascii() {
+            return Err(EditError::NotAscii(c));
+        }
+
+        let mut line = self.row_text(row).to_string();
+        line.replace_range(column..column + 1, c.encode_utf8(&mut [0; 4]));
+        let (parts, symbols) = Self::parse_line(row, &line, &self.dialect)
+            .map_err(|_| EditError::NumberTooLarge { row, column })?;
+        self.text[row * self.width + column] = c as u8;
+
+        let row_cells = &self.cells[row * self.width..(row + 1) * self.width];
+        let mut part_ids = BTreeSet::new();
+        let mut symbol_ids = BTreeSet::new();
+        for cell in row_cells {
+            match *cell {
+                Cell::Part(id) => part_ids.insert(id),
+                Cell::Symbol(id) => symbol_ids.insert(id),
+                Cell::Empty => false,
+            };
+        }
+        // Removing the highest ids first means the ones still to be removed
+        // are never the ones moved into the gaps
+        for id in part_ids.into_iter().rev() {
+            self.remove_part_id(id);
+        }
+        for id in symbol_ids.into_iter().rev() {
+            self.remove_symbol_id(id);
+        }
+
+        for part in parts {
+            for column in part.start..part.end {
+                self.cells[row * self.width + column] = Cell::Part(self.raw_parts.len());
+            }
+            self.raw_parts.push(part);
+            self.valid.push(false);
+        }
+        for symbol in symbols {
+            self.cells[row * self.width + symbol.column] = Cell::Symbol(self.raw_symbols.len());
+            self.raw_symbols.push(symbol);
+            self.symbol_parts.push(vec![]);
+        }
+
+        self.refresh_rows(max(row, 1) - 1..(row + 2).min(self.height()));
+        Ok(())
+    }
+
+    /// Puts a symbol in an empty cell
+    pub fn insert_symbol(
+        &mut self,
+        row: usize,
+        column: usize,
+        symbol: char,
+    ) -> Result<(), EditError> {
+        if !self.dialect.is_symbol(symbol) {
+            return Err(EditError::NotASymbol(symbol));
+        }
+        match self.cell_at(row, column) {
+            Some(Cell::Empty) => self.set_cell(row, column, symbol),
+            Some(_) => Err(EditError::Occupied { row, column }),
+            None => Err(EditError::OutOfBounds { row, column }),
+        }
+    }
+
+    /// Replaces a symbol with the background
+    pub fn remove_symbol(&mut self, row: usize, column: usize) -> Result<(), EditError> {
+        match self.cell_at(row, column) {
+            Some(Cell::Symbol(_)) => self.set_cell(row, column, self.dialect.background_char()),
+            Some(_) => Err(EditError::NoSymbol { row, column }),
+            None => Err(EditError::OutOfBounds { row, column }),
+        }
+    }
+
+    fn cell_at(&self, row: usize, column: usize) -> Option<Cell> {
+        if row < self.height() && column < self.width {
+            Some(self.cells[row * self.width + column])
+        } else {
+            None
+        }
+    }
+
+    /// Removes a part by moving the last part into its place
+    fn remove_part_id(&mut self, id: usize) {
+        let part = self.raw_parts.swap_remove(id);
+        self.valid.swap_remove(id);
+        for column in part.start..part.end {
+            self.cells[part.row * self.width + column] = Cell::Empty;
+        }
+
+        let moved_from = self.raw_parts.len();
+        if id == moved_from {
+            return;
+        }
+        let moved = self.raw_parts[id];
+        for column in moved.start..moved.end {
+            self.cells[moved.row * self.width + column] = Cell::Part(id);
+        }
+        let neighbours = self
+            .neighbours(moved.row, moved.start, moved.end)
+            .collect::<Vec<_>>();
+        for cell in neighbours {
+            if let Cell::Symbol(symbol) = cell {
+                for part in self.symbol_parts[symbol].iter_mut() {
+                    if *part == moved_from {
+                        *part = id;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Removes a symbol by moving the last symbol into its place
+    fn remove_symbol_id(&mut self, id: usize) {
+        let symbol = self.raw_symbols.swap_remove(id);
+        self.symbol_parts.swap_remove(id);
+        self.cells[symbol.row * self.width + symbol.column] = Cell::Empty;
+
+        if let Some(moved) = self.raw_symbols.get(id) {
+            self.cells[moved.row * self.width + moved.column] = Cell::Symbol(id);
+        }
+    }
+
+    /// Rows are checked to be ASCII when parsed and edited, so they are
+    /// always valid UTF-8
+    fn row_text(&self, row: usize) -> &str {
+        std::str::from_utf8(&self.text[row * self.width..(row + 1) * self.width]).unwrap()
+    }
+
+    /// The row and column of every cell around a run of `start..end` on
+    /// `row`, skipping any that are off the edge of the schematic
+    fn neighbour_positions(
+        &self,
+        row: usize,
+        start: usize,
+        end: usize,
+    ) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let height = self.height();
+        let rows = max(row, 1) - 1..=row + 1;
+        rows.filter(move |&r| r < height).flat_map(move |r| {
+            (max(start, 1) - 1..=end)
+                .filter(move |&c| c < self.width && (r != row || c < start || c >= end))
+                .map(move |c| (r, c))
+        })
+    }
+
+    /// The contents of every cell around a run of `start..end` on `row`
+    fn neighbours(&self, row: usize, start: usize, end: usize) -> impl Iterator<Item = Cell> + '_ {
+        self.neighbour_positions(row, start, end)
+            .map(move |(r, c)| self.cells[r * self.width + c])
+    }
+
+    /// Groups of parts and symbols that are joined by chains of cells
+    /// touching horizontally, vertically or diagonally, in the order their
+    /// first cell appears
+    pub fn components(&self) -> Vec<Component> {
+        let mut seen = vec![false; self.cells.len()];
+        let mut components = vec![];
+
+        for first in 0..self.cells.len() {
+            if seen[first] || self.cells[first] == Cell::Empty {
+                continue;
+            }
+
+            let mut part_ids = BTreeSet::new();
+            let mut symbol_ids = BTreeSet::new();
+            let mut bounds = Bounds::at(first / self.width, first % self.width);
+            let mut stack = vec![first];
+            seen[first] = true;
+
+            while let Some(i) = stack.pop() {
+                let (row, column) = (i / self.width, i % self.width);
+                bounds.include(row, column);
+                match self.cells[i] {
+                    Cell::Part(id) => part_ids.insert(id),
+                    Cell::Symbol(id) => symbol_ids.insert(id),
+                    Cell::Empty => unreachable!("empty cells aren't visited"),
+                };
+
+                for (r, c) in self.neighbour_positions(row, column, column + 1) {
+                    let j = r * self.width + c;
+                    if !seen[j] && self.cells[j] != Cell::Empty {
+                        seen[j] = true;
+                        stack.push(j);
+                    }
+                }
+            }
+
+            let mut parts = part_ids
+                .into_iter()
+                .map(|id| self.raw_parts[id])
+                .collect::<Vec<_>>();
+            parts.sort_by_key(|p| (p.row, p.start));
+            let mut symbols = symbol_ids
+                .into_iter()
+                .map(|id| self.raw_symbols[id])
+                .collect::<Vec<_>>();
+            symbols.sort_by_key(|s| (s.row, s.column));
+
+            components.push(Component {
+                parts,
+                symbols,
+                bounds,
+            });
+        }
+
+        components
+    }
+
+    pub fn component_summary(&self) -> ComponentSummary {
+        let components = self.components();
+        // The first of the largest, so ties go to the one nearest the top
+        let largest = components.iter().rev().max_by_key(|c| c.size()).cloned();
+        let isolated_parts = components
+            .iter()
+            .filter(|c| c.parts.len() == 1 && c.symbols.is_empty())
+            .map(|c| c.parts[0])
+            .collect();
+
+        ComponentSummary {
+            count: components.len(),
+            largest,
+            isolated_parts,
         }
     }
 
     pub fn valid_parts(&self) -> Vec<Part> {
-        let parts = &self.raw_parts[..];
+        let mut parts = self
+            .raw_parts
+            .iter()
+            .zip(&self.valid)
+            .filter(|(_, &valid)| valid)
+            .map(|(p, _)| *p)
+            .collect::<Vec<_>>();
+        parts.sort_by_key(|p| (p.row, p.start));
         parts
-            .into_iter()
-            .filter_map(|p| {
-                if Self::is_adjacent(p, &self.raw_symbols) {
-                    Some(*p)
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>()
     }
 
-    pub fn gears(&self) -> Vec<Gear> {
-        self.raw_symbols
+    fn is_valid_part(&self, part: &Part) -> bool {
+        self.neighbours(part.row, part.start, part.end)
+            .any(|cell| matches!(cell, Cell::Symbol(_)))
+    }
+
+    /// Redraws the schematic from what was parsed, marking valid and invalid
+    /// parts and the gears found by `rule`
+    pub fn render(&self, rule: &GearRule, mode: RenderMode) -> String {
+        let gears = self
+            .gears(rule)
             .iter()
-            .filter_map(|sym| {
-                let adj_part_nos = self.adjacent_parts_nos(sym);
-                if adj_part_nos.len() == 2 {
-                    Some(Gear {
-                        ratio: adj_part_nos[0] * adj_part_nos[1],
-                    })
-                } else {
-                    None
+            .map(|g| (g.symbol.row, g.symbol.column))
+            .collect::<BTreeSet<_>>();
+
+        let mut marks = Vec::with_capacity(self.cells.len());
+        let mut text = Vec::with_capacity(self.cells.len());
+        for (i, cell) in self.cells.iter().enumerate() {
+            let mark = match *cell {
+                Cell::Empty => Mark::Background,
+                Cell::Part(id) if self.valid[id] => Mark::ValidPart,
+                Cell::Part(_) => Mark::InvalidPart,
+                Cell::Symbol(id) => {
+                    let symbol = &self.raw_symbols[id];
+                    if gears.contains(&(symbol.row, symbol.column)) {
+                        Mark::Gear
+                    } else {
+                        Mark::Symbol
+                    }
+                }
+            };
+            marks.push(mark);
+            text.push(self.text[i] as char);
+        }
+
+        let mut result = String::new();
+        if mode == RenderMode::Html {
+            result.push_str(HTML_HEADER);
+        }
+        for (marks, text) in marks
+            .chunks(self.width.max(1))
+            .zip(text.chunks(self.width.max(1)))
+        {
+            match mode {
+                RenderMode::Plain => {
+                    result.extend(text);
+                    result.push('\n');
+                    let markers = marks.iter().map(|m| m.marker()).collect::<String>();
+                    result.push_str(markers.trim_end());
+                }
+                RenderMode::Ansi | RenderMode::Html => {
+                    // Consecutive cells with the same mark share one span
+                    let mut start = 0;
+                    while start < marks.len() {
+                        let end = (start..marks.len())
+                            .find(|&i| marks[i] != marks[start])
+                            .unwrap_or(marks.len());
+                        let run = text[start..end].iter().collect::<String>();
+                        result.push_str(&marks[start].wrap(&run, mode));
+                        start = end;
+                    }
                 }
+            }
+            result.push('\n');
+        }
+        if mode == RenderMode::Html {
+            result.push_str(HTML_FOOTER);
+        }
+
+        result
+    }
+
+    pub fn gears(&self, rule: &GearRule) -> Vec<Gear> {
+        let mut gears = self
+            .raw_symbols
+            .iter()
+            .zip(&self.symbol_parts)
+            .filter_map(|(sym, ids)| {
+                let mut parts = ids.iter().map(|&id| self.raw_parts[id]).collect::<Vec<_>>();
+                parts.sort_by_key(|p| (p.row, p.start));
+                rule.gear(sym, parts)
             })
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>();
+        gears.sort_by_key(|g| (g.symbol.row, g.symbol.column));
+        gears
+    }
+
+    fn adjacent_part_ids(&self, symbol: &Symbol) -> Vec<usize> {
+        let mut ids = self
+            .neighbours(symbol.row, symbol.column, symbol.column + 1)
+            .filter_map(|cell| match cell {
+                Cell::Part(id) => Some(id),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        // A part spans several cells, so it can border a symbol more than once
+        ids.sort();
+        ids.dedup();
+        ids
     }
 
-    fn adjacent_parts_nos(&self, symbol: &Symbol) -> Vec<i32> {
+    /// `valid_parts` without the grid index, checking every part against
+    /// every symbol
+    fn valid_parts_by_scan(&self) -> Vec<Part> {
         self.raw_parts
             .iter()
-            .filter_map(|p| {
-                if p.is_adjacent(symbol) {
-                    Some(p.number)
-                } else {
-                    None
-                }
+            .filter(|p| Self::is_adjacent(p, &self.raw_symbols))
+            .copied()
+            .collect::<Vec<_>>()
+    }
+
+    /// `gears` without the grid index, checking every symbol against every
+    /// part
+    fn gears_by_scan(&self, rule: &GearRule) -> Vec<Gear> {
+        self.raw_symbols
+            .iter()
+            .filter_map(|sym| {
+                let parts = self
+                    .raw_parts
+                    .iter()
+                    .filter(|p| p.is_adjacent(sym))
+                    .copied()
+                    .collect::<Vec<_>>();
+                rule.gear(sym, parts)
             })
             .collect::<Vec<_>>()
     }
 
-    fn parse_line(line_no: usize, line: &str) -> (Vec<Part>, Vec<Symbol>) {
-        let parts = PART_REGEX
+    fn parse_line(
+        line_no: usize,
+        line: &str,
+        dialect: &Dialect,
+    ) -> Result<(Vec<Part>, Vec<Symbol>), SchematicError> {
+        let part_regex = if dialect.signed_numbers {
+            &SIGNED_PART_REGEX
+        } else {
+            &PART_REGEX
+        };
+        let parts = part_regex
             .find_iter(line)
             .map(|m| {
-                let number = m.as_str().parse::<i32>().expect("Failed to parse number");
-                let start = m.start();
-                let end = m.end();
-
-                Part {
+                let number =
+                    m.as_str()
+                        .parse::<i32>()
+                        .map_err(|_| SchematicError::NumberTooLarge {
+                            line: line_no + 1,
+                            column: m.start() + 1,
+                        })?;
+                Ok(Part {
                     number,
                     row: line_no,
-                    start,
-                    end,
-                }
+                    start: m.start(),
+                    end: m.end(),
+                })
             })
-            .collect();
+            .collect::<Result<Vec<_>, _>>()?;
 
-        let symbols = SYMBOL_REGEX
-            .find_iter(line)
-            .map(|m| Symbol {
+        // A sign belongs to its number, so it can't also be a symbol
+        let in_part = |column: usize| parts.iter().any(|p| (p.start..p.end).contains(&column));
+        let symbols = line
+            .char_indices()
+            .filter(|&(column, c)| dialect.is_symbol(c) && !in_part(column))
+            .map(|(column, symbol)| Symbol {
                 row: line_no,
-                column: m.start(),
-                symbol: m
-                    .as_str()
-                    .chars()
-                    .next()
-                    .expect("Could read symbol character"),
+                column,
+                symbol,
             })
             .collect();
 
-        (parts, symbols)
+        Ok((parts, symbols))
     }
 
     fn is_adjacent(part: &Part, symbols: &[Symbol]) -> bool {
-        symbols.into_iter().any(|s| part.is_adjacent(s))
+        symbols.iter().any(|s| part.is_adjacent(s))
     }
 }
 
@@ -146,11 +827,321 @@ impl Part {
     }
 }
 
-#[derive(PartialEq, Debug, Copy, Clone)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RenderMode {
+    /// Colours for a terminal
+    Ansi,
+    /// A line of markers under every row
+    Plain,
+    /// A standalone page with a class on every highlighted span
+    Html,
+}
+
+impl FromStr for RenderMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "ansi" => Ok(RenderMode::Ansi),
+            "plain" => Ok(RenderMode::Plain),
+            "html" => Ok(RenderMode::Html),
+            _ => Err(format!("Unknown render mode {}", s)),
+        }
+    }
+}
+
+/// How a cell is highlighted when rendering
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mark {
+    Background,
+    ValidPart,
+    InvalidPart,
+    Gear,
+    Symbol,
+}
+
+impl Mark {
+    fn marker(&self) -> char {
+        match self {
+            Mark::Background | Mark::Symbol => ' ',
+            Mark::ValidPart => '+',
+            Mark::InvalidPart => '-',
+            Mark::Gear => 'G',
+        }
+    }
+
+    fn wrap(&self, text: &str, mode: RenderMode) -> String {
+        match (mode, self) {
+            (RenderMode::Plain, _) | (_, Mark::Background) => text.to_string(),
+            (RenderMode::Ansi, _) => {
+                let colour = match self {
+                    Mark::ValidPart => "32",
+                    Mark::InvalidPart => "31",
+                    Mark::Gear => "1;33",
+                    _ => "36",
+                };
+                format!("\x1b[{}m{}\x1b[0m", colour, text)
+            }
+            (RenderMode::Html, _) => {
+                let class = match self {
+                    Mark::ValidPart => "valid",
+                    Mark::InvalidPart => "invalid",
+                    Mark::Gear => "gear",
+                    _ => "symbol",
+                };
+                let escaped = text
+                    .replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;");
+                format!("<span class=\"{}\">{}</span>", class, escaped)
+            }
+        }
+    }
+}
+
+const HTML_HEADER: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<style>
+.valid { color: green; }
+.invalid { color: red; }
+.gear { color: orange; font-weight: bold; }
+.symbol { color: teal; }
+</style>
+</head>
+<body>
+<pre>
+";
+
+const HTML_FOOTER: &str = "</pre>
+</body>
+</html>
+";
+
+/// A change to a parsed schematic
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Edit {
+    Set {
+        row: usize,
+        column: usize,
+        c: char,
+    },
+    InsertSymbol {
+        row: usize,
+        column: usize,
+        symbol: char,
+    },
+    RemoveSymbol {
+        row: usize,
+        column: usize,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+enum EditError {
+    OutOfBounds {
+        row: usize,
+        column: usize,
+    },
+    NotAscii(char),
+    NotASymbol(char),
+    Occupied {
+        row: usize,
+        column: usize,
+    },
+    NoSymbol {
+        row: usize,
+        column: usize,
+    },
+    /// The edit would join digits into a number that doesn't fit in an `i32`
+    NumberTooLarge {
+        row: usize,
+        column: usize,
+    },
+}
+
+impl Display for EditError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EditError::OutOfBounds { row, column } => {
+                write!(f, "Row {}, column {} is outside the schematic", row, column)
+            }
+            EditError::NotAscii(c) => write!(f, "{:?} is not an ASCII character", c),
+            EditError::NotASymbol(c) => write!(f, "{:?} is not a symbol", c),
+            EditError::Occupied { row, column } => {
+                write!(f, "Row {}, column {} is not empty", row, column)
+            }
+            EditError::NoSymbol { row, column } => {
+                write!(f, "Row {}, column {} is not a symbol", row, column)
+            }
+            EditError::NumberTooLarge { row, column } => write!(
+                f,
+                "Row {}, column {} would make a number that is too large",
+                row, column
+            ),
+        }
+    }
+}
+
+/// Reads a cell as `row,column` or `row,column,character`
+fn parse_cell(inp: &str) -> Result<(usize, usize, Option<char>), String> {
+    let invalid = || format!("{} is not a valid cell", inp);
+    let mut fields = inp.splitn(3, ',');
+    let row = fields
+        .next()
+        .and_then(|r| r.parse().ok())
+        .ok_or_else(invalid)?;
+    let column = fields
+        .next()
+        .and_then(|c| c.parse().ok())
+        .ok_or_else(invalid)?;
+    let c = match fields.next() {
+        Some(c) if c.chars().count() == 1 => c.chars().next(),
+        Some(_) => return Err(invalid()),
+        None => None,
+    };
+    Ok((row, column, c))
+}
+
+/// A symbol that a `GearRule` accepts, with the parts around it
+#[derive(PartialEq, Debug, Clone)]
 struct Gear {
+    symbol: Symbol,
+    parts: Vec<Part>,
     ratio: i32,
 }
 
+/// Decides which symbols are gears and what their ratio is
+#[derive(Debug, Clone)]
+struct GearRule {
+    /// The symbols that can be gears, or any symbol if empty
+    symbols: Vec<char>,
+    /// How many adjacent parts a gear must have
+    parts: RangeInclusive<usize>,
+    ratio: fn(&[Part]) -> i32,
+}
+
+impl Default for GearRule {
+    /// The puzzle's rule: a `*` next to exactly two parts, whose ratio is the
+    /// product of their numbers
+    fn default() -> Self {
+        GearRule {
+            symbols: vec!['*'],
+            parts: 2..=2,
+            ratio: product_ratio,
+        }
+    }
+}
+
+impl GearRule {
+    fn gear(&self, symbol: &Symbol, parts: Vec<Part>) -> Option<Gear> {
+        let symbol_matches = self.symbols.is_empty() || self.symbols.contains(&symbol.symbol);
+        if !symbol_matches || !self.parts.contains(&parts.len()) {
+            return None;
+        }
+
+        Some(Gear {
+            symbol: *symbol,
+            ratio: (self.ratio)(&parts),
+            parts,
+        })
+    }
+}
+
+fn product_ratio(parts: &[Part]) -> i32 {
+    parts.iter().map(|p| p.number).product()
+}
+
+fn sum_ratio(parts: &[Part]) -> i32 {
+    parts.iter().map(|p| p.number).sum()
+}
+
+/// Reads a count of parts as `n`, `min-max` or `min-` for no upper bound
+fn parse_count_range(inp: &str) -> Result<RangeInclusive<usize>, String> {
+    let parse = |n: &str| {
+        n.trim()
+            .parse::<usize>()
+            .map_err(|_| format!("{} is not a valid part count", inp))
+    };
+    match inp.split_once('-') {
+        None => parse(inp).map(|n| n..=n),
+        Some((min, "")) => Ok(parse(min)?..=usize::MAX),
+        Some((min, max)) => Ok(parse(min)?..=parse(max)?),
+    }
+}
+
+/// A group of parts and symbols connected through neighbouring cells
+#[derive(PartialEq, Debug, Clone)]
+struct Component {
+    parts: Vec<Part>,
+    symbols: Vec<Symbol>,
+    bounds: Bounds,
+}
+
+impl Component {
+    /// The number of parts and symbols
+    fn size(&self) -> usize {
+        self.parts.len() + self.symbols.len()
+    }
+}
+
+/// The smallest rectangle holding a set of cells. Like a part's `end`, the
+/// bottom and right edges are one past the last cell.
+#[derive(PartialEq, Debug, Copy, Clone)]
+struct Bounds {
+    top: usize,
+    left: usize,
+    bottom: usize,
+    right: usize,
+}
+
+impl Bounds {
+    fn at(row: usize, column: usize) -> Bounds {
+        Bounds {
+            top: row,
+            left: column,
+            bottom: row + 1,
+            right: column + 1,
+        }
+    }
+
+    fn include(&mut self, row: usize, column: usize) {
+        self.top = self.top.min(row);
+        self.left = self.left.min(column);
+        self.bottom = self.bottom.max(row + 1);
+        self.right = self.right.max(column + 1);
+    }
+}
+
+#[derive(PartialEq, Debug)]
+struct ComponentSummary {
+    count: usize,
+    largest: Option<Component>,
+    /// Parts that touch nothing, not even other parts
+    isolated_parts: Vec<Part>,
+}
+
+impl Display for ComponentSummary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} components", self.count)?;
+        if let Some(largest) = &self.largest {
+            let b = largest.bounds;
+            write!(
+                f,
+                ", the largest has {} parts and {} symbols in rows {}..{}, columns {}..{}",
+                largest.parts.len(),
+                largest.symbols.len(),
+                b.top,
+                b.bottom,
+                b.left,
+                b.right
+            )?;
+        }
+        write!(f, ", {} isolated parts", self.isolated_parts.len())
+    }
+}
+
 #[derive(PartialEq, Debug, Copy, Clone)]
 struct Symbol {
     row: usize,
@@ -159,12 +1150,106 @@ struct Symbol {
 }
 
 static PART_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"\d+").unwrap());
-static SYMBOL_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"[^\d\.]").unwrap());
+static SIGNED_PART_REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"-?\d+").unwrap());
+
+/// How a schematic is written. The default is the puzzle's: `.` for empty
+/// cells, unsigned numbers and anything else as a symbol.
+#[derive(Debug, Clone)]
+struct Dialect {
+    /// Characters for empty cells
+    background: Vec<char>,
+    /// Whether a `-` directly before a number is its sign rather than a
+    /// symbol
+    signed_numbers: bool,
+    /// Which of the remaining characters are symbols. Any others are treated
+    /// as empty cells.
+    symbols: SymbolClass,
+}
+
+impl Dialect {
+    fn is_symbol(&self, c: char) -> bool {
+        !c.is_ascii_digit() && !self.background.contains(&c) && self.symbols.contains(c)
+    }
+
+    /// The character written for empty cells
+    fn background_char(&self) -> char {
+        self.background.first().copied().unwrap_or('.')
+    }
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Dialect {
+            background: vec!['.'],
+            signed_numbers: false,
+            symbols: SymbolClass::Any,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum SymbolClass {
+    Any,
+    /// ASCII punctuation, so letters and whitespace are empty cells
+    Punctuation,
+    Only(Vec<char>),
+}
+
+impl SymbolClass {
+    fn contains(&self, c: char) -> bool {
+        match self {
+            SymbolClass::Any => true,
+            SymbolClass::Punctuation => c.is_ascii_punctuation(),
+            SymbolClass::Only(symbols) => symbols.contains(&c),
+        }
+    }
+}
+
+impl FromStr for SymbolClass {
+    type Err = String;
+
+    /// `any`, `punctuation`, or the symbols themselves
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "any" => Ok(SymbolClass::Any),
+            "punctuation" => Ok(SymbolClass::Punctuation),
+            "" => Err("No symbols given".to_string()),
+            _ => Ok(SymbolClass::Only(s.chars().collect())),
+        }
+    }
+}
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// Removes the indentation shared by every line after the first, along
+    /// with blank lines at either end, so fixtures can be indented with the
+    /// code around them
+    fn dedent(input: &str) -> String {
+        let lines = input.lines().collect::<Vec<_>>();
+        let indent = lines
+            .iter()
+            .skip(1)
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| line.len() - line.trim_start().len())
+            .min()
+            .unwrap_or(0);
+        let lines = lines
+            .iter()
+            .enumerate()
+            .map(|(i, line)| match i {
+                0 => line.trim_start(),
+                _ => line.get(indent..).unwrap_or(""),
+            })
+            .collect::<Vec<_>>();
+        lines.join("\n").trim_matches('\n').to_string()
+    }
+
+    fn parse(input: &str) -> Schematic {
+        Schematic::parse(dedent(input).lines()).unwrap()
+    }
+
     #[test]
     fn test_part_1() {
         let input = "467..114..
@@ -178,7 +1263,7 @@ mod tests {
         ...$.*....
         .664.598..";
 
-        let schematic = Schematic::parse(input.lines());
+        let schematic = parse(input);
         let part_nos = schematic
             .valid_parts()
             .iter()
@@ -201,14 +1286,42 @@ mod tests {
         ...$.*....
         .664.598..";
 
-        let schematic = Schematic::parse(input.lines());
-        let gears = schematic.gears();
-        assert_eq!(gears, vec![Gear { ratio: 16345 }, Gear { ratio: 451490 }]);
+        let schematic = parse(input);
+        let gears = schematic.gears(&GearRule::default());
+        assert_eq!(
+            gears.iter().map(|g| g.ratio).collect::<Vec<_>>(),
+            vec![16345, 451490]
+        );
+        assert_eq!(
+            gears[0],
+            Gear {
+                symbol: Symbol {
+                    row: 1,
+                    column: 3,
+                    symbol: '*'
+                },
+                parts: vec![
+                    Part {
+                        number: 467,
+                        row: 0,
+                        start: 0,
+                        end: 3
+                    },
+                    Part {
+                        number: 35,
+                        row: 2,
+                        start: 2,
+                        end: 4
+                    }
+                ],
+                ratio: 16345
+            }
+        );
     }
 
     #[test]
     fn test_parse_parts() {
-        let (parts, symbols) = Schematic::parse_line(1, "467..114..");
+        let (parts, symbols) = Schematic::parse_line(1, "467..114..", &Dialect::default()).unwrap();
         assert_eq!(
             parts,
             vec![
@@ -231,7 +1344,7 @@ mod tests {
 
     #[test]
     fn test_parse_symbols() {
-        let (parts, symbols) = Schematic::parse_line(3, "...$.*....");
+        let (parts, symbols) = Schematic::parse_line(3, "...$.*....", &Dialect::default()).unwrap();
 
         assert_eq!(parts.len(), 0);
         assert_eq!(
@@ -253,7 +1366,7 @@ mod tests {
 
     #[test]
     fn test_parse_mixed() {
-        let (parts, symbols) = Schematic::parse_line(8, "617*......");
+        let (parts, symbols) = Schematic::parse_line(8, "617*......", &Dialect::default()).unwrap();
         assert_eq!(
             parts,
             vec![Part {
@@ -310,4 +1423,516 @@ mod tests {
             assert!(part.is_adjacent(&symbol), "{} should be adjacent", i);
         }
     }
+
+    #[test]
+    fn test_index_matches_scan() {
+        let lines = generate_schematic(80, 60, 7);
+        let schematic = Schematic::parse(lines.iter()).unwrap();
+
+        assert!(!schematic.valid_parts().is_empty());
+        let rule = GearRule {
+            symbols: vec![],
+            parts: 1..=3,
+            ratio: sum_ratio,
+        };
+        assert!(!schematic.gears(&rule).is_empty());
+        assert_eq!(schematic.valid_parts(), schematic.valid_parts_by_scan());
+        assert_eq!(schematic.gears(&rule), schematic.gears_by_scan(&rule));
+    }
+
+    #[test]
+    fn test_neighbours_at_edges() {
+        let schematic = parse("12*\n..3");
+        assert_eq!(
+            schematic.neighbours(0, 0, 2).collect::<Vec<_>>(),
+            vec![Cell::Symbol(0), Cell::Empty, Cell::Empty, Cell::Part(1)]
+        );
+        assert_eq!(schematic.valid_parts().len(), 2);
+    }
+
+    #[test]
+    fn test_only_stars_are_gears() {
+        let schematic = parse("12#3\n4*5.");
+        let gears = schematic.gears(&GearRule::default());
+        assert_eq!(gears.len(), 0);
+
+        let rule = GearRule {
+            symbols: vec!['*', '#'],
+            parts: 3..=3,
+            ..GearRule::default()
+        };
+        let gears = schematic.gears(&rule);
+        assert_eq!(
+            gears
+                .iter()
+                .map(|g| (g.symbol.symbol, g.ratio))
+                .collect::<Vec<_>>(),
+            vec![('#', 180), ('*', 240)]
+        );
+    }
+
+    #[test]
+    fn test_parse_count_range() {
+        assert_eq!(parse_count_range("2"), Ok(2..=2));
+        assert_eq!(parse_count_range("1-3"), Ok(1..=3));
+        assert_eq!(parse_count_range("2-"), Ok(2..=usize::MAX));
+        assert!(parse_count_range("two").is_err());
+    }
+
+    #[test]
+    fn test_render_plain() {
+        let schematic = parse("467..114..\n...*......\n..35..6&3.");
+        assert_eq!(
+            schematic.render(&GearRule::default(), RenderMode::Plain),
+            "467..114..\n\
+             +++  ---\n\
+             ...*......\n\
+             \x20  G\n\
+             ..35..6&3.\n\
+             \x20 ++  + +\n"
+        );
+    }
+
+    #[test]
+    fn test_render_ansi() {
+        let schematic = parse("12..\n.#.5");
+        assert_eq!(
+            schematic.render(&GearRule::default(), RenderMode::Ansi),
+            "\x1b[32m12\x1b[0m..\n.\x1b[36m#\x1b[0m.\x1b[31m5\x1b[0m\n"
+        );
+    }
+
+    #[test]
+    fn test_render_html() {
+        let schematic = parse("1*2&");
+        let html = schematic.render(&GearRule::default(), RenderMode::Html);
+        assert!(html.starts_with(HTML_HEADER));
+        assert!(html.ends_with(HTML_FOOTER));
+        assert!(html.contains(
+            "<span class=\"valid\">1</span><span class=\"gear\">*</span>\
+             <span class=\"valid\">2</span><span class=\"symbol\">&amp;</span>\n"
+        ));
+    }
+
+    #[test]
+    fn test_render_keeps_leading_zeros() {
+        let schematic = parse("007*");
+        assert_eq!(
+            schematic.render(&GearRule::default(), RenderMode::Plain),
+            "007*\n+++\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_keeps_columns() {
+        let schematic = Schematic::parse(["  12*", "....."].iter()).unwrap();
+        assert_eq!(
+            schematic.raw_parts,
+            vec![Part {
+                number: 12,
+                row: 0,
+                start: 2,
+                end: 4
+            }]
+        );
+        let star = schematic.raw_symbols.iter().find(|s| s.symbol == '*');
+        assert_eq!(star.map(|s| s.column), Some(4));
+    }
+
+    #[test]
+    fn test_parse_crlf() {
+        let schematic = parse("12*.\r\n....\r\n");
+        assert_eq!(schematic.width, 4);
+        assert_eq!(schematic.valid_parts().len(), 1);
+    }
+
+    #[test]
+    fn test_parse_ragged_rows() {
+        let err = Schematic::parse("12*.\n...\n....\n.....".lines())
+            .err()
+            .unwrap();
+        assert_eq!(
+            err,
+            SchematicError::Ragged {
+                expected: 4,
+                rows: vec![
+                    RaggedRow { line: 2, width: 3 },
+                    RaggedRow { line: 4, width: 5 }
+                ]
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Rows must all be 4 wide, but line 2 is 3 wide, line 4 is 5 wide"
+        );
+    }
+
+    #[test]
+    fn test_parse_non_ascii_rows() {
+        let err = Schematic::parse(["....", "§.12"].iter()).err().unwrap();
+        assert_eq!(
+            err,
+            SchematicError::NotAscii {
+                line: 2,
+                column: 1,
+                found: '§'
+            }
+        );
+        assert_eq!(
+            err.to_string(),
+            "Line 2, column 1: '§' is not ASCII, and cells are one byte wide"
+        );
+    }
+
+    #[test]
+    fn test_dedent() {
+        let input = "
+            467..
+            ..*..
+              .1.
+        ";
+        assert_eq!(dedent(input), "467..\n..*..\n  .1.");
+        assert_eq!(dedent("12*\n    ...\n    .3."), "12*\n...\n.3.");
+    }
+
+    #[test]
+    fn test_signed_numbers() {
+        let lines = dedent(
+            "..-12..
+             .*..-..
+             3-4....",
+        );
+        let unsigned = Schematic::parse(lines.lines()).unwrap();
+        assert_eq!(
+            unsigned
+                .valid_parts()
+                .iter()
+                .map(|p| p.number)
+                .collect::<Vec<_>>(),
+            vec![12, 3, 4]
+        );
+
+        let dialect = Dialect {
+            signed_numbers: true,
+            ..Dialect::default()
+        };
+        let signed = Schematic::parse_with(lines.lines(), &dialect).unwrap();
+        assert_eq!(
+            signed
+                .valid_parts()
+                .iter()
+                .map(|p| p.number)
+                .collect::<Vec<_>>(),
+            vec![-12, 3, -4]
+        );
+        assert_eq!(
+            signed
+                .raw_symbols
+                .iter()
+                .map(|s| s.symbol)
+                .collect::<Vec<_>>(),
+            vec!['*', '-']
+        );
+    }
+
+    #[test]
+    fn test_background_and_symbol_classes() {
+        let lines = ["  12  a", " #   *3"];
+        let dialect = Dialect {
+            background: vec![' '],
+            symbols: SymbolClass::Punctuation,
+            ..Dialect::default()
+        };
+        let schematic = Schematic::parse_with(lines.iter(), &dialect).unwrap();
+        assert_eq!(
+            schematic
+                .raw_symbols
+                .iter()
+                .map(|s| s.symbol)
+                .collect::<Vec<_>>(),
+            vec!['#', '*']
+        );
+        assert_eq!(schematic.valid_parts().len(), 2);
+
+        let dialect = Dialect {
+            symbols: SymbolClass::Only(vec!['*']),
+            ..dialect
+        };
+        let schematic = Schematic::parse_with(lines.iter(), &dialect).unwrap();
+        assert_eq!(
+            schematic
+                .valid_parts()
+                .iter()
+                .map(|p| p.number)
+                .collect::<Vec<_>>(),
+            vec![3]
+        );
+    }
+
+    #[test]
+    fn test_render_keeps_ignored_characters() {
+        let dialect = Dialect {
+            symbols: SymbolClass::Punctuation,
+            ..Dialect::default()
+        };
+        let mut schematic = Schematic::parse_with(["ab12*", "c...."].iter(), &dialect).unwrap();
+        assert_eq!(
+            schematic.render(&GearRule::default(), RenderMode::Plain),
+            "ab12*\n  ++\nc....\n\n"
+        );
+
+        schematic.set_cell(1, 1, 'd').unwrap();
+        assert_eq!(
+            schematic.render(&GearRule::default(), RenderMode::Plain),
+            "ab12*\n  ++\ncd...\n\n"
+        );
+    }
+
+    #[test]
+    fn test_parse_symbol_class() {
+        assert_eq!("any".parse(), Ok(SymbolClass::Any));
+        assert_eq!("punctuation".parse(), Ok(SymbolClass::Punctuation));
+        assert_eq!("*#".parse(), Ok(SymbolClass::Only(vec!['*', '#'])));
+        assert!("".parse::<SymbolClass>().is_err());
+    }
+
+    #[test]
+    fn test_components() {
+        let schematic = parse(
+            "467..114..
+             ...*......
+             ..35..633.
+             ......#...
+             617*......
+             .....+.58.
+             ..592.....
+             ......755.
+             ...$.*....
+             .664.598..",
+        );
+        let components = schematic.components();
+
+        assert_eq!(
+            components
+                .iter()
+                .map(|c| c.parts.iter().map(|p| p.number).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+            vec![
+                vec![467, 35],
+                vec![114],
+                vec![633],
+                vec![617],
+                vec![592],
+                vec![58],
+                vec![755, 598],
+                vec![664],
+            ]
+        );
+        assert_eq!(components[6].symbols[0].symbol, '*');
+        assert_eq!(
+            components[6].bounds,
+            Bounds {
+                top: 7,
+                left: 5,
+                bottom: 10,
+                right: 9
+            }
+        );
+        assert_eq!(components[7].symbols[0].symbol, '$');
+    }
+
+    #[test]
+    fn test_component_summary() {
+        let schematic = parse(
+            "1.22.
+             .....
+             3*4..
+             ..5.6",
+        );
+        let summary = schematic.component_summary();
+
+        assert_eq!(summary.count, 4);
+        assert_eq!(
+            summary.largest.map(|c| c.bounds),
+            Some(Bounds {
+                top: 2,
+                left: 0,
+                bottom: 4,
+                right: 3
+            })
+        );
+        assert_eq!(
+            summary
+                .isolated_parts
+                .iter()
+                .map(|p| p.number)
+                .collect::<Vec<_>>(),
+            vec![1, 22, 6]
+        );
+    }
+
+    #[test]
+    fn test_edits() {
+        let mut schematic = parse(
+            "467..114..
+             ...*......
+             ..35..633.",
+        );
+
+        schematic.insert_symbol(1, 8, '#').unwrap();
+        assert_eq!(
+            schematic
+                .valid_parts()
+                .iter()
+                .map(|p| p.number)
+                .collect::<Vec<_>>(),
+            vec![467, 114, 35, 633]
+        );
+
+        // Filling the gap joins two parts into one
+        schematic.set_cell(0, 3, '0').unwrap();
+        schematic.set_cell(0, 4, '0').unwrap();
+        assert_eq!(
+            schematic
+                .valid_parts()
+                .iter()
+                .map(|p| p.number)
+                .collect::<Vec<_>>(),
+            vec![46700114, 35, 633]
+        );
+        assert_eq!(
+            schematic
+                .gears(&GearRule::default())
+                .iter()
+                .map(|g| g.ratio)
+                .collect::<Vec<_>>(),
+            vec![46700114 * 35]
+        );
+
+        schematic.remove_symbol(1, 3).unwrap();
+        schematic.insert_symbol(1, 5, '*').unwrap();
+        let rule = GearRule {
+            ratio: sum_ratio,
+            ..GearRule::default()
+        };
+        let gears = schematic.gears(&rule);
+        assert_eq!(
+            gears[0].parts.iter().map(|p| p.number).collect::<Vec<_>>(),
+            vec![46700114, 633]
+        );
+        assert_eq!(
+            schematic.render(&rule, RenderMode::Plain),
+            "46700114..\n\
+             ++++++++\n\
+             .....*..#.\n\
+             \x20    G\n\
+             ..35..633.\n\
+             \x20 --  +++\n"
+        );
+    }
+
+    #[test]
+    fn test_edit_errors() {
+        let mut schematic = parse("1*.\n...");
+        assert_eq!(
+            schematic.set_cell(2, 0, '#'),
+            Err(EditError::OutOfBounds { row: 2, column: 0 })
+        );
+        assert_eq!(schematic.set_cell(0, 2, 'é'), Err(EditError::NotAscii('é')));
+        assert_eq!(
+            schematic.insert_symbol(0, 2, '5'),
+            Err(EditError::NotASymbol('5'))
+        );
+        assert_eq!(
+            schematic.insert_symbol(0, 0, '#'),
+            Err(EditError::Occupied { row: 0, column: 0 })
+        );
+        assert_eq!(
+            schematic.remove_symbol(1, 1),
+            Err(EditError::NoSymbol { row: 1, column: 1 })
+        );
+        assert_eq!(schematic.valid_parts().len(), 1);
+    }
+
+    #[test]
+    fn test_edit_number_too_large() {
+        let mut schematic = parse("99999.99999\n.....#.....");
+        assert_eq!(
+            schematic.set_cell(0, 5, '9'),
+            Err(EditError::NumberTooLarge { row: 0, column: 5 })
+        );
+        assert_eq!(
+            schematic.render(&GearRule::default(), RenderMode::Plain),
+            parse("99999.99999\n.....#.....").render(&GearRule::default(), RenderMode::Plain)
+        );
+        assert_eq!(schematic.valid_parts().len(), 2);
+
+        let err = Schematic::parse(["12345678901*"].iter()).err().unwrap();
+        assert_eq!(err, SchematicError::NumberTooLarge { line: 1, column: 1 });
+        assert_eq!(err.to_string(), "Line 1, column 1: number is too large");
+    }
+
+    #[test]
+    fn test_edit_keeps_signs() {
+        let dialect = Dialect {
+            signed_numbers: true,
+            ..Dialect::default()
+        };
+        let mut schematic = Schematic::parse_with(["-0.*"].iter(), &dialect).unwrap();
+        schematic.set_cell(0, 2, '#').unwrap();
+        assert_eq!(
+            schematic.render(&GearRule::default(), RenderMode::Plain),
+            "-0#*\n++\n"
+        );
+        assert_eq!(
+            schematic.raw_parts,
+            vec![Part {
+                number: 0,
+                row: 0,
+                start: 0,
+                end: 2
+            }]
+        );
+    }
+
+    #[test]
+    fn test_edits_match_reparsing() {
+        let mut lines = generate_schematic(30, 20, 11)
+            .into_iter()
+            .map(|line| line.into_bytes())
+            .collect::<Vec<_>>();
+        let mut schematic =
+            Schematic::parse(lines.iter().map(|l| std::str::from_utf8(l).unwrap())).unwrap();
+        let rule = GearRule {
+            symbols: vec![],
+            parts: 1..=4,
+            ratio: sum_ratio,
+        };
+
+        let mut rng = XorShift::new(0x5eed);
+        for _ in 0..500 {
+            let state = rng.next_u64();
+            let row = (state % 20) as usize;
+            let column = (state / 20 % 30) as usize;
+            let c = b"......0123*#-"[(state / 600 % 13) as usize];
+
+            lines[row][column] = c;
+            schematic.set_cell(row, column, c as char).unwrap();
+
+            let reparsed =
+                Schematic::parse(lines.iter().map(|l| std::str::from_utf8(l).unwrap())).unwrap();
+            assert_eq!(schematic.valid_parts(), reparsed.valid_parts());
+            assert_eq!(schematic.gears(&rule), reparsed.gears(&rule));
+            assert_eq!(schematic.components(), reparsed.components());
+        }
+    }
+
+    #[test]
+    fn test_parse_cell() {
+        assert_eq!(parse_cell("3,4"), Ok((3, 4, None)));
+        assert_eq!(parse_cell("3,4,*"), Ok((3, 4, Some('*'))));
+        assert_eq!(parse_cell("3,4,,"), Ok((3, 4, Some(','))));
+        assert!(parse_cell("3").is_err());
+        assert!(parse_cell("3,4,ab").is_err());
+    }
 }
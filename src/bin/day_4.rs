@@ -1,11 +1,23 @@
+use std::{collections::BTreeMap, fmt::Display};
+
 use advent_of_code_2023::{io::read_lines, regex};
 use once_cell::sync::Lazy;
 use regex::Regex;
 
 fn main() {
-    let cards = read_lines("inputs/day_4.txt")
-        .map(|line| Card::from(&line))
-        .collect::<Vec<_>>();
+    let mut breakdown = false;
+
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--breakdown" => breakdown = true,
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+
+    let cards = parse_cards(read_lines("inputs/day_4.txt")).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
 
     println!(
         "Day 4, Star 1: {}",
@@ -15,39 +27,96 @@ fn main() {
             .sum::<i32>()
     );
 
-    let n_cards = total_scratchcards(&cards[..]);
+    let instances = scratchcard_instances(&cards[..]).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(1);
+    });
+    let n_cards = instances.iter().map(|i| i.total).sum::<usize>();
     println!("Day 4, Star 2: {}", n_cards);
+
+    if breakdown {
+        for card in &instances {
+            println!("{}", card);
+        }
+    }
 }
 
-static CARD_REGEX: Lazy<Regex> = regex!(r"Card .+: (?<winning>.+) \| (?<mine>.+)");
+static CARD_REGEX: Lazy<Regex> = regex!(r"Card +(?<id>\d+): (?<winning>.+) \| (?<mine>.+)");
 
 struct Card {
-    winning: Vec<i32>,
-    mine: Vec<i32>,
+    id: usize,
+    winning: NumberSet,
+    mine: Vec<u16>,
 }
 
-impl Card {
+impl From<&str> for Card {
     fn from(inp: &str) -> Self {
-        let captures = CARD_REGEX.captures(inp).unwrap();
+        Card::parse(inp).unwrap_or_else(|| panic!("{} is not a valid card", inp))
+    }
+}
+
+impl Card {
+    fn parse(inp: &str) -> Option<Self> {
+        let captures = CARD_REGEX.captures(inp)?;
+        let id = unwrap_group(&captures, "id").parse::<usize>().ok()?;
         let winning = unwrap_group(&captures, "winning");
         let mine = unwrap_group(&captures, "mine");
 
-        Card {
-            winning: parse_ints(winning),
-            mine: parse_ints(mine),
-        }
+        Some(Card {
+            id,
+            winning: parse_ints(winning)?.into_iter().collect(),
+            mine: parse_ints(mine)?,
+        })
     }
 
+    /// The number of distinct winning numbers on the card. A number that
+    /// appears more than once in `mine` still only matches once.
     fn matches(&self) -> i32 {
-        let mut matches = 0;
+        let mut seen = NumberSet::default();
+        self.mine
+            .iter()
+            .filter(|&&num| self.winning.contains(num) && seen.insert(num))
+            .count() as i32
+    }
+}
 
-        for num in &self.mine {
-            if self.winning.contains(num) {
-                matches += 1;
-            }
+/// A set of card numbers, one bit each. Numbers are at most `u16::MAX`, so
+/// the set never grows past 8 KiB.
+#[derive(Debug, Default, PartialEq)]
+struct NumberSet {
+    bits: Vec<u64>,
+}
+
+impl NumberSet {
+    fn index(num: u16) -> (usize, u64) {
+        let num = usize::from(num);
+        (num / 64, 1 << (num % 64))
+    }
+
+    fn contains(&self, num: u16) -> bool {
+        let (word, bit) = Self::index(num);
+        self.bits.get(word).is_some_and(|w| w & bit != 0)
+    }
+
+    /// Returns whether the number wasn't already in the set
+    fn insert(&mut self, num: u16) -> bool {
+        let (word, bit) = Self::index(num);
+        if word >= self.bits.len() {
+            self.bits.resize(word + 1, 0);
         }
+        let added = self.bits[word] & bit == 0;
+        self.bits[word] |= bit;
+        added
+    }
+}
 
-        matches
+impl FromIterator<u16> for NumberSet {
+    fn from_iter<T: IntoIterator<Item = u16>>(iter: T) -> Self {
+        let mut set = NumberSet::default();
+        for num in iter {
+            set.insert(num);
+        }
+        set
     }
 }
 
@@ -59,28 +128,150 @@ fn score(matches: i32) -> i32 {
     }
 }
 
-fn total_scratchcards(cards: &[Card]) -> usize {
-    let mut instances: Vec<usize> = vec![1; cards.len()];
+/// How many instances of a card were held, counting the original
+#[derive(Debug, PartialEq)]
+struct CardInstances {
+    card: usize,
+    total: usize,
+    /// The earlier cards that won copies of this one, and how many each won
+    won_from: Vec<(usize, usize)>,
+}
 
-    for (idx, card) in cards.iter().enumerate() {
-        let card_instances = instances[idx];
-        let matches = card.matches();
-        for i in 1..(matches + 1) {
-            instances[idx + i as usize] += card_instances;
+impl Display for CardInstances {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Card {}: {} instances (1 original",
+            self.card, self.total
+        )?;
+        for (card, copies) in &self.won_from {
+            write!(f, ", {} from card {}", copies, card)?;
+        }
+        write!(f, ")")
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum CardError {
+    /// Counting lines from 1
+    Unparsable {
+        line: usize,
+    },
+    Duplicate {
+        card: usize,
+    },
+    /// A card won copies of cards beyond the end of the table
+    PastEnd {
+        card: usize,
+        matches: usize,
+        following: usize,
+    },
+    /// A card won a copy of a card that isn't in the table
+    Missing {
+        card: usize,
+        won_by: usize,
+    },
+}
+
+impl Display for CardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CardError::Unparsable { line } => write!(f, "Line {} is not a card", line),
+            CardError::Duplicate { card } => write!(f, "Card {} appears more than once", card),
+            CardError::PastEnd {
+                card,
+                matches,
+                following,
+            } => write!(
+                f,
+                "Card {} has {} matches, but only {} cards follow it",
+                card, matches, following
+            ),
+            CardError::Missing { card, won_by } => write!(
+                f,
+                "Card {} won a copy of card {}, which is missing",
+                won_by, card
+            ),
+        }
+    }
+}
+
+/// Parses every card and sorts them by id, so the lines can be in any order
+fn parse_cards<T: Iterator<Item = U>, U: AsRef<str>>(lines: T) -> Result<Vec<Card>, CardError> {
+    let mut cards = vec![];
+    for (i, line) in lines.enumerate() {
+        let card = Card::parse(line.as_ref()).ok_or(CardError::Unparsable { line: i + 1 })?;
+        cards.push(card);
+    }
+
+    cards.sort_by_key(|card| card.id);
+    if let Some(pair) = cards.windows(2).find(|pair| pair[0].id == pair[1].id) {
+        return Err(CardError::Duplicate { card: pair[0].id });
+    }
+
+    Ok(cards)
+}
+
+/// Each card wins copies of the cards with the next ids, however the cards
+/// are ordered. Every card that is won must be in the table.
+fn scratchcard_instances(cards: &[Card]) -> Result<Vec<CardInstances>, CardError> {
+    let mut matches = BTreeMap::new();
+    for card in cards {
+        if matches.insert(card.id, card.matches() as usize).is_some() {
+            return Err(CardError::Duplicate { card: card.id });
+        }
+    }
+    let mut instances = matches
+        .keys()
+        .map(|&id| {
+            let instances = CardInstances {
+                card: id,
+                total: 1,
+                won_from: vec![],
+            };
+            (id, instances)
+        })
+        .collect::<BTreeMap<_, _>>();
+    let last = matches.keys().last().copied().unwrap_or(0);
+
+    // Copies are only won of later cards, so each total is final by the time
+    // its card is reached
+    for (&id, &matches) in &matches {
+        let card_instances = instances[&id].total;
+        // Ids can be as large as `usize` allows, so nothing here adds to them
+        // unchecked
+        let end = match id.checked_add(matches) {
+            Some(end) if end <= last => end,
+            _ => {
+                return Err(CardError::PastEnd {
+                    card: id,
+                    matches,
+                    following: instances.range(id..).count() - 1,
+                });
+            }
+        };
+
+        for won in (id..=end).skip(1) {
+            let won = instances.get_mut(&won).ok_or(CardError::Missing {
+                card: won,
+                won_by: id,
+            })?;
+            won.total += card_instances;
+            won.won_from.push((id, card_instances));
         }
     }
 
-    instances.iter().sum::<usize>()
+    Ok(instances.into_values().collect())
 }
 
 fn unwrap_group<'a>(captures: &'a regex::Captures, name: &str) -> &'a str {
     captures.name(name).unwrap().as_str()
 }
 
-fn parse_ints(inp: &str) -> Vec<i32> {
+fn parse_ints(inp: &str) -> Option<Vec<u16>> {
     inp.split_whitespace()
-        .map(|s| s.parse::<i32>().unwrap())
-        .collect::<Vec<_>>()
+        .map(|s| s.parse::<u16>().ok())
+        .collect::<Option<Vec<_>>>()
 }
 
 #[cfg(test)]
@@ -91,7 +282,8 @@ mod test {
     fn test_parse_card() {
         let card = Card::from("Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53");
 
-        assert_eq!(card.winning, vec![41, 48, 83, 86, 17]);
+        assert_eq!(card.id, 1);
+        assert_eq!(card.winning, NumberSet::from_iter([41, 48, 83, 86, 17]));
         assert_eq!(card.mine, vec![83, 86, 6, 31, 17, 9, 48, 53]);
     }
 
@@ -103,6 +295,21 @@ mod test {
         assert_eq!(matches, 4);
     }
 
+    #[test]
+    fn test_duplicates_match_once() {
+        let card = Card::from("Card 1: 41 48 48 | 48 48 41 7 41");
+        assert_eq!(card.matches(), 2);
+    }
+
+    #[test]
+    fn test_number_set() {
+        let mut set = NumberSet::from_iter([0, 63, 64, 200]);
+        assert!(set.contains(0) && set.contains(63) && set.contains(64) && set.contains(200));
+        assert!(!set.contains(1) && !set.contains(65) && !set.contains(1000));
+        assert!(set.insert(1));
+        assert!(!set.insert(64));
+    }
+
     #[test]
     fn test_score_of_zero_matches_is_zero() {
         assert_eq!(score(0), 0);
@@ -129,11 +336,128 @@ mod test {
         Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
         Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11";
 
-        let cards = input
-            .split('\n')
-            .map(|line| Card::from(line))
-            .collect::<Vec<_>>();
+        let cards = parse_cards(input.lines()).unwrap();
+
+        let instances = scratchcard_instances(&cards).unwrap();
+        assert_eq!(instances.iter().map(|i| i.total).sum::<usize>(), 30);
+        assert_eq!(
+            instances.iter().map(|i| i.total).collect::<Vec<_>>(),
+            vec![1, 2, 4, 8, 14, 1]
+        );
+        assert_eq!(
+            instances[4],
+            CardInstances {
+                card: 5,
+                total: 14,
+                won_from: vec![(1, 1), (3, 4), (4, 8)]
+            }
+        );
+        assert_eq!(
+            instances[4].to_string(),
+            "Card 5: 14 instances (1 original, 1 from card 1, 4 from card 3, 8 from card 4)"
+        );
+    }
+
+    #[test]
+    fn test_copies_past_the_end() {
+        let cards = [
+            Card::from("Card 1: 1 2 | 1 3"),
+            Card::from("Card 2: 1 2 | 1 2"),
+        ];
+
+        assert_eq!(
+            scratchcard_instances(&cards),
+            Err(CardError::PastEnd {
+                card: 2,
+                matches: 2,
+                following: 0
+            })
+        );
+        assert_eq!(
+            scratchcard_instances(&cards).unwrap_err().to_string(),
+            "Card 2 has 2 matches, but only 0 cards follow it"
+        );
+    }
+
+    #[test]
+    fn test_largest_card_id() {
+        let cards = [Card::from("Card 18446744073709551615: 1 | 1")];
+        assert_eq!(
+            scratchcard_instances(&cards),
+            Err(CardError::PastEnd {
+                card: usize::MAX,
+                matches: 1,
+                following: 0
+            })
+        );
+
+        let cards = [Card::from("Card 18446744073709551615: 1 | 2")];
+        assert_eq!(scratchcard_instances(&cards).map(|i| i[0].total), Ok(1));
+    }
+
+    #[test]
+    fn test_shuffled_cards() {
+        let input = "Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+        Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+        Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11
+        Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+        Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+        Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1";
+
+        let cards = parse_cards(input.lines()).unwrap();
+        assert_eq!(
+            cards.iter().map(|c| c.id).collect::<Vec<_>>(),
+            vec![1, 2, 3, 4, 5, 6]
+        );
+
+        let instances = scratchcard_instances(&cards).unwrap();
+        assert_eq!(instances.iter().map(|i| i.total).sum::<usize>(), 30);
+    }
+
+    #[test]
+    fn test_gapped_cards() {
+        let cards = [
+            Card::from("Card 1: 1 2 | 1 3"),
+            Card::from("Card 3: 1 2 | 4 5"),
+            Card::from("Card 4: 1 2 | 1 2"),
+            Card::from("Card 7: 1 2 | 4 5"),
+        ];
 
-        assert_eq!(total_scratchcards(&cards), 30);
+        assert_eq!(
+            scratchcard_instances(&cards[..2]),
+            Err(CardError::Missing { card: 2, won_by: 1 })
+        );
+        assert_eq!(
+            scratchcard_instances(&cards[1..]),
+            Err(CardError::Missing { card: 5, won_by: 4 })
+        );
+        assert_eq!(
+            CardError::Missing { card: 5, won_by: 4 }.to_string(),
+            "Card 4 won a copy of card 5, which is missing"
+        );
+    }
+
+    #[test]
+    fn test_parse_card_errors() {
+        assert_eq!(
+            parse_cards(["Card 1: 1 | 1", "Card 2 1 | 1"].iter()).err(),
+            Some(CardError::Unparsable { line: 2 })
+        );
+        assert_eq!(
+            parse_cards(["Card 1: 1 x | 2"].iter()).err(),
+            Some(CardError::Unparsable { line: 1 })
+        );
+        assert_eq!(
+            parse_cards(["Card 1: -1 | 2"].iter()).err(),
+            Some(CardError::Unparsable { line: 1 })
+        );
+        assert_eq!(
+            parse_cards(["Card 1: 2000000000 | 2"].iter()).err(),
+            Some(CardError::Unparsable { line: 1 })
+        );
+        assert_eq!(
+            parse_cards(["Card 2: 1 | 1", "Card 1: 1 | 1", "Card 2: 3 | 4"].iter()).err(),
+            Some(CardError::Duplicate { card: 2 })
+        );
     }
 }
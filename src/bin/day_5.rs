@@ -61,6 +61,7 @@ struct Almanac {
 }
 
 impl Default for Almanac {
+    #[allow(clippy::single_range_in_vec_init)]
     fn default() -> Self {
         Almanac {
             seeds: vec![],
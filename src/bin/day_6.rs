@@ -1,6 +1,6 @@
 fn main() {
     // This input is so simple we can just hardcode it
-    let races = vec![
+    let races = [
         Race {
             time: 41,
             max_distance: 249,
@@ -43,7 +43,7 @@ impl Race {
     ///
     /// The index in the array is the time held while the value is the distance
     fn distances(&self) -> Vec<usize> {
-        (0..(self.time as usize + 1))
+        (0..(self.time + 1))
             .map(|time_held| {
                 let speed = time_held;
                 let time_remaining = self.time - time_held;
@@ -1,12 +1,46 @@
-use std::{cmp::Ordering, collections::BTreeMap, str::FromStr};
+use std::{
+    cmp::{Ordering, Reverse},
+    collections::BTreeMap,
+    fmt::Display,
+    ops::RangeInclusive,
+    str::FromStr,
+};
 
 use advent_of_code_2023::io::read_lines;
 
 fn main() {
+    let mut poker_file = None;
+    let mut explain = None;
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            // Poker hands are written as value and suit pairs, e.g. `2H3D5S9CKD 10`
+            "--poker" => poker_file = Some(args.next().expect("--poker requires an input file")),
+            "--explain" => {
+                let format = args.next().expect("--explain requires table or csv");
+                explain = Some(format.parse::<ExplainFormat>().unwrap());
+            }
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+
+    if let Some(file_name) = poker_file {
+        let input = parse_hand_bids(read_lines(&file_name));
+        report("Total poker winnings", &input, &Poker, explain);
+        return;
+    }
+
     let input = parse_hand_bids(read_lines("inputs/day_7.txt"));
-    let joker_winnings = joker_total_winnings(&input);
-    println!("Total winnings: {}", total_winnings(input));
-    println!("Total winnings with jokers: {}", joker_winnings);
+    report("Total winnings", &input, &Standard, explain);
+    report("Total winnings with jokers", &input, &Jokers, explain);
+}
+
+fn report<R: Rules>(title: &str, hands: &[HandBid], rules: &R, explain: Option<ExplainFormat>) {
+    if let Some(format) = explain {
+        print!("{}", explain_ranking(hands, rules, format));
+    }
+    println!("{}: {}", title, total_winnings(hands, rules));
 }
 
 struct HandBid {
@@ -29,8 +63,19 @@ fn parse_hand_bids<T: AsRef<str>, I: Iterator<Item = T>>(lines: I) -> Vec<HandBi
     lines.map(|line| line.as_ref().parse().unwrap()).collect()
 }
 
-fn total_winnings(mut hands: Vec<HandBid>) -> u32 {
-    hands.sort_by(|a, b| a.hand.partial_cmp(&b.hand).unwrap());
+/// Sorts the hands from weakest to strongest under the given rules.
+///
+/// Identical hands are equally strong, so ties are broken by the lower bid
+/// first and then by input order, keeping the result deterministic.
+fn ranked_hands<'a, R: Rules>(hands: &'a [HandBid], rules: &R) -> Vec<&'a HandBid> {
+    let mut hands = hands.iter().collect::<Vec<_>>();
+    hands.sort_by_key(|hb| (rules.strength(&hb.hand), hb.bid));
+    hands
+}
+
+/// Sums each bid multiplied by the rank of its hand under the given rules
+fn total_winnings<R: Rules>(hands: &[HandBid], rules: &R) -> u32 {
+    let hands = ranked_hands(hands, rules);
     let mut result = 0;
 
     for (rank, hand) in hands.iter().enumerate() {
@@ -40,62 +85,264 @@ fn total_winnings(mut hands: Vec<HandBid>) -> u32 {
     result
 }
 
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ExplainFormat {
+    Table,
+    Csv,
+}
+
+impl FromStr for ExplainFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "table" => Ok(ExplainFormat::Table),
+            "csv" => Ok(ExplainFormat::Csv),
+            _ => Err(format!("Unknown explain format {}", s)),
+        }
+    }
+}
+
+/// Lists every hand in ranked order with the card strengths used to break
+/// ties, its base rank and its rank under `rules`, its final position and
+/// what it contributes to the total winnings.
+fn explain_ranking<R: Rules>(hands: &[HandBid], rules: &R, format: ExplainFormat) -> String {
+    let mut result = match format {
+        ExplainFormat::Table => format!(
+            "{:>8} {:<10} {:<18} {:<13} {:<13} {:>6} {:>10}\n",
+            "position", "hand", "strengths", "base rank", "rules rank", "bid", "winnings"
+        ),
+        ExplainFormat::Csv => "position,hand,strengths,base_rank,rules_rank,bid,winnings\n".into(),
+    };
+
+    for (i, hb) in ranked_hands(hands, rules).iter().enumerate() {
+        let position = i as u32 + 1;
+        let strengths = rules
+            .strength(&hb.hand)
+            .1
+            .map(|strength| strength.to_string())
+            .join(" ");
+        let base_rank = hb.hand.rank;
+        let rules_rank = rules.rank(&hb.hand);
+        let winnings = hb.bid * position;
+
+        result += &match format {
+            ExplainFormat::Table => format!(
+                "{:>8} {:<10} {:<18} {:<13} {:<13} {:>6} {:>10}\n",
+                position, hb.hand, strengths, base_rank, rules_rank, hb.bid, winnings
+            ),
+            ExplainFormat::Csv => format!(
+                "{},{},{},{},{},{},{}\n",
+                position, hb.hand, strengths, base_rank, rules_rank, hb.bid, winnings
+            ),
+        };
+    }
+
+    result
+}
+
+/// A variant of the Camel Cards rules, deciding how cards are ordered and
+/// which hand type a set of cards makes.
+trait Rules {
+    /// The strength of a card when comparing hands of the same type
+    fn card_strength(&self, card: Card) -> u8;
+
+    /// Whether the card can stand in for any other card
+    fn is_wildcard(&self, card: Card) -> bool;
+
+    /// The type of the hand under these rules, by default the best type any
+    /// substitution of the wildcards can make
+    fn rank(&self, hand: &Hand) -> HandRank {
+        hand.substituted_rank(self)
+    }
+
+    /// A key that sorts hands from weakest to strongest
+    fn strength(&self, hand: &Hand) -> (HandRank, [u8; 5]) {
+        (
+            self.rank(hand),
+            hand.cards.map(|card| self.card_strength(card)),
+        )
+    }
+}
+
+/// The part one rules, where every card is its face value
+struct Standard;
+
+impl Rules for Standard {
+    fn card_strength(&self, card: Card) -> u8 {
+        card.value
+    }
+
+    fn is_wildcard(&self, _card: Card) -> bool {
+        false
+    }
+
+    fn rank(&self, hand: &Hand) -> HandRank {
+        hand.rank
+    }
+}
+
+/// The part two rules, where `J` is a joker that acts as whatever card makes
+/// the strongest hand type but is the weakest card when breaking ties
+struct Jokers;
+
 const JOKER_VALUE: u8 = 11;
 
-fn apply_jokers(h: &Hand) -> Hand {
-    let rank = h.joker_rank();
-    let mut cards: [Card; 5] = h.cards;
-    for c in &mut cards {
-        if c.value == JOKER_VALUE {
-            c.value = 1
+impl Rules for Jokers {
+    fn card_strength(&self, card: Card) -> u8 {
+        if self.is_wildcard(card) {
+            1
+        } else {
+            card.value
         }
     }
-    Hand { cards, rank }
+
+    fn is_wildcard(&self, card: Card) -> bool {
+        card.value == JOKER_VALUE
+    }
+
+    fn rank(&self, hand: &Hand) -> HandRank {
+        hand.joker_rank()
+    }
 }
 
-fn joker_total_winnings(hands: &Vec<HandBid>) -> u32 {
-    let joker_hands = hands
-        .iter()
-        .map(|hb: &HandBid| HandBid {
-            hand: apply_jokers(&hb.hand),
-            ..*hb
-        })
-        .collect::<Vec<_>>();
-    total_winnings(joker_hands)
+/// Standard poker rules for suited hands. Straights and flushes count, aces
+/// can be high or low in a straight, and ties are broken by comparing the
+/// largest groups of cards first and then the kickers.
+struct Poker;
+
+impl Rules for Poker {
+    fn card_strength(&self, card: Card) -> u8 {
+        card.value
+    }
+
+    fn is_wildcard(&self, _card: Card) -> bool {
+        false
+    }
+
+    fn rank(&self, hand: &Hand) -> HandRank {
+        let flush = is_flush(&hand.cards);
+        match (straight_high_card(&hand.cards), flush) {
+            (Some(_), true) => HandRank::StraightFlush,
+            (Some(_), false) => HandRank::Straight,
+            (None, true) => hand.rank.max(HandRank::Flush),
+            (None, false) => hand.rank,
+        }
+    }
+
+    fn strength(&self, hand: &Hand) -> (HandRank, [u8; 5]) {
+        let kickers = match straight_high_card(&hand.cards) {
+            Some(high) => [high, high - 1, high - 2, high - 3, high - 4],
+            None => {
+                let cardinality = cardinality(&hand.cards);
+                let mut values = hand.cards.map(|card| card.value);
+                values.sort_by_key(|value| Reverse((cardinality[value], *value)));
+                values
+            }
+        };
+        (self.rank(hand), kickers)
+    }
+}
+
+const ACE_VALUE: u8 = 14;
+
+/// The value of the highest card if the hand is a straight, where an ace-low
+/// straight is five high
+fn straight_high_card(cards: &[Card; 5]) -> Option<u8> {
+    let mut values = cards.map(|card| card.value);
+    values.sort();
+
+    if values == [2, 3, 4, 5, ACE_VALUE] {
+        Some(5)
+    } else if values.windows(2).all(|pair| pair[1] == pair[0] + 1) {
+        Some(values[4])
+    } else {
+        None
+    }
+}
+
+fn is_flush(cards: &[Card; 5]) -> bool {
+    cards[0].suit.is_some() && cards.iter().all(|card| card.suit == cards[0].suit)
+}
+
+/// Every card value from `2` up to `A`
+const CARD_VALUES: RangeInclusive<u8> = 2..=14;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Suit {
+    Clubs,
+    Diamonds,
+    Hearts,
+    Spades,
+}
+
+impl From<char> for Suit {
+    fn from(c: char) -> Self {
+        match c {
+            'C' => Suit::Clubs,
+            'D' => Suit::Diamonds,
+            'H' => Suit::Hearts,
+            'S' => Suit::Spades,
+            _ => panic!("Unknown suit {}", c),
+        }
+    }
+}
+
+impl Display for Suit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let c = match self {
+            Suit::Clubs => 'C',
+            Suit::Diamonds => 'D',
+            Suit::Hearts => 'H',
+            Suit::Spades => 'S',
+        };
+        write!(f, "{}", c)
+    }
 }
 
-#[derive(Debug, Clone, Copy)]
+/// A single card. Camel Cards are unsuited, so `suit` is only set for poker
+/// hands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 struct Card {
     value: u8,
+    suit: Option<Suit>,
 }
 
 impl From<char> for Card {
     fn from(c: char) -> Self {
         Card {
             value: match c {
-                'A' => 14,
+                'A' => ACE_VALUE,
                 'K' => 13,
                 'Q' => 12,
                 'J' => JOKER_VALUE,
                 'T' => 10,
                 _ => c.to_digit(10).unwrap() as u8,
             },
+            suit: None,
         }
     }
 }
 
-impl PartialEq for Card {
-    fn eq(&self, other: &Self) -> bool {
-        self.value == other.value
-    }
-}
-
-impl PartialOrd for Card {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        self.value.partial_cmp(&other.value)
+impl Display for Card {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let c = match self.value {
+            ACE_VALUE => 'A',
+            13 => 'K',
+            12 => 'Q',
+            JOKER_VALUE => 'J',
+            10 => 'T',
+            v => char::from_digit(v as u32, 10).unwrap(),
+        };
+        write!(f, "{}", c)?;
+        if let Some(suit) = self.suit {
+            write!(f, "{}", suit)?;
+        }
+        Ok(())
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Hand {
     cards: [Card; 5],
     rank: HandRank,
@@ -106,7 +353,7 @@ impl Hand {
         let n_jokers = self
             .cards
             .iter()
-            .filter(|&c| c.value == JOKER_VALUE)
+            .filter(|&&c| Jokers.is_wildcard(c))
             .count();
         if n_jokers == 0 {
             self.rank
@@ -132,50 +379,83 @@ impl Hand {
             }
         }
     }
+
+    /// Finds the best rank by trying every non-wildcard card value in place of
+    /// the wildcards.
+    ///
+    /// A hand type is always maximised by making every wildcard the same card,
+    /// so only one substitution per value is needed.
+    fn substituted_rank<R: Rules + ?Sized>(&self, rules: &R) -> HandRank {
+        CARD_VALUES
+            .map(|value| Card { value, suit: None })
+            .filter(|&card| !rules.is_wildcard(card))
+            .map(|substitute| {
+                let cards = self.cards.map(|card| {
+                    if rules.is_wildcard(card) {
+                        substitute
+                    } else {
+                        card
+                    }
+                });
+                HandRank::from(&cards)
+            })
+            .max()
+            .unwrap_or(self.rank)
+    }
 }
 
 impl FromStr for Hand {
     type Err = ();
 
+    /// Parses either five unsuited cards (`32T3K`) or five cards each followed
+    /// by their suit (`3H2DTS3CKD`)
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let cards: [Card; 5] = s
-            .chars()
-            .map(Card::from)
-            .collect::<Vec<_>>()
-            .try_into()
-            .unwrap();
+        let chars = s.chars().collect::<Vec<_>>();
+        let cards: Vec<Card> = match chars.len() {
+            5 => chars.into_iter().map(Card::from).collect(),
+            10 => chars
+                .chunks(2)
+                .map(|c| Card {
+                    suit: Some(Suit::from(c[1])),
+                    ..Card::from(c[0])
+                })
+                .collect(),
+            _ => return Err(()),
+        };
+        let cards: [Card; 5] = cards.try_into().unwrap();
         let rank = HandRank::from(&cards[..]);
         Ok(Hand { cards, rank })
     }
 }
 
+impl Display for Hand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cards = self.cards.map(|card| card.to_string()).concat();
+        f.pad(&cards)
+    }
+}
+
 impl From<&str> for Hand {
     fn from(s: &str) -> Self {
         s.parse().unwrap()
     }
 }
 
-impl PartialEq for Hand {
-    fn eq(&self, other: &Self) -> bool {
-        self.rank == other.rank && self.cards == other.cards
+impl Ord for Hand {
+    /// Hands are ordered by their strength under the standard rules: rank
+    /// first and then card by card from the left. Suits only break ties
+    /// between hands of the same values, so only identical hands are equal.
+    fn cmp(&self, other: &Self) -> Ordering {
+        Standard
+            .strength(self)
+            .cmp(&Standard.strength(other))
+            .then_with(|| self.cards.cmp(&other.cards))
     }
 }
 
 impl PartialOrd for Hand {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        let rank_ordering = self.rank.partial_cmp(&other.rank)?;
-        if rank_ordering == Ordering::Equal {
-            for i in 0..5 {
-                let card_ordering = self.cards[i].partial_cmp(&other.cards[i])?;
-                if card_ordering != Ordering::Equal {
-                    return Some(card_ordering);
-                }
-            }
-            // This should be impossible
-            None
-        } else {
-            Some(rank_ordering)
-        }
+        Some(self.cmp(other))
     }
 }
 
@@ -187,15 +467,25 @@ fn cardinality(cards: &[Card]) -> BTreeMap<u8, u8> {
     cardinality
 }
 
-#[derive(Debug, PartialEq, PartialOrd, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
 enum HandRank {
     HighCard = 1,
     OnePair = 2,
     TwoPair = 3,
     ThreeOfAKind = 4,
-    FullHouse = 5,
-    FourOfAKind = 6,
-    FiveOfAKind = 7,
+    // Straights and flushes only exist under poker rules
+    Straight = 5,
+    Flush = 6,
+    FullHouse = 7,
+    FourOfAKind = 8,
+    StraightFlush = 9,
+    FiveOfAKind = 10,
+}
+
+impl Display for HandRank {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.pad(&format!("{:?}", self))
+    }
 }
 
 impl From<&[Card]> for HandRank {
@@ -246,11 +536,26 @@ mod test {
         assert_eq!(
             hand.cards,
             [
-                Card { value: 3 },
-                Card { value: 2 },
-                Card { value: 10 },
-                Card { value: 3 },
-                Card { value: 13 },
+                Card {
+                    value: 3,
+                    suit: None
+                },
+                Card {
+                    value: 2,
+                    suit: None
+                },
+                Card {
+                    value: 10,
+                    suit: None
+                },
+                Card {
+                    value: 3,
+                    suit: None
+                },
+                Card {
+                    value: 13,
+                    suit: None
+                },
             ]
         );
     }
@@ -328,6 +633,35 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_identical_hands_are_equal() {
+        let hand = Hand::from("3K3K3");
+        assert_eq!(hand.cmp(&Hand::from("3K3K3")), Ordering::Equal);
+    }
+
+    #[test]
+    fn test_total_winnings_duplicate_hands() {
+        let input = "32T3K 5
+        KK677 10
+        32T3K 2";
+
+        let inputs = parse_hand_bids(input.lines());
+        // Duplicate one pair hands are ranked 1 and 2 with the lower bid first
+        assert_eq!(total_winnings(&inputs, &Standard), 2 + 5 * 2 + 10 * 3);
+    }
+
+    #[test]
+    fn test_joker_card_strength() {
+        let hand = Hand::from("JKKK2");
+        let other = Hand::from("QQQQ2");
+
+        assert!(Standard.strength(&hand) < Standard.strength(&other));
+        assert!(
+            Jokers.strength(&hand) < Jokers.strength(&other),
+            "Jokers are the weakest card when breaking ties"
+        );
+    }
+
     #[test]
     fn test_total_winnings() {
         let input = "32T3K 765
@@ -337,7 +671,7 @@ mod test {
         QQQJA 483";
 
         let inputs = parse_hand_bids(input.lines());
-        assert_eq!(total_winnings(inputs), 6440);
+        assert_eq!(total_winnings(&inputs, &Standard), 6440);
     }
 
     #[test]
@@ -380,6 +714,175 @@ mod test {
         }
     }
 
+    /// Every way of choosing `len` card values, ignoring order, each in
+    /// ascending order
+    fn multisets(len: usize, lowest: u8) -> Vec<Vec<u8>> {
+        if len == 0 {
+            return vec![vec![]];
+        }
+        (lowest..=*CARD_VALUES.end())
+            .flat_map(|value| {
+                multisets(len - 1, value).into_iter().map(move |mut rest| {
+                    rest.insert(0, value);
+                    rest
+                })
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_joker_rank_matches_substitution() {
+        // A hand's rank doesn't depend on the order of its cards, so checking
+        // every multiset of values covers every hand and number of jokers
+        let hands = multisets(5, *CARD_VALUES.start());
+        assert_eq!(hands.len(), 6188);
+
+        for values in hands {
+            let cards = [0, 1, 2, 3, 4].map(|i| Card {
+                value: values[i],
+                suit: None,
+            });
+            let hand = Hand {
+                cards,
+                rank: HandRank::from(&cards),
+            };
+
+            assert_eq!(
+                hand.joker_rank(),
+                hand.substituted_rank(&Jokers),
+                "{:?} should have the same rank by lookup and substitution",
+                cards
+            );
+        }
+    }
+
+    #[test]
+    fn test_parse_suited_hand() {
+        let hand = Hand::from("AS2HTDTC3S");
+        assert_eq!(
+            hand.cards[0],
+            Card {
+                value: ACE_VALUE,
+                suit: Some(Suit::Spades)
+            }
+        );
+        assert_eq!(hand.rank, HandRank::OnePair);
+    }
+
+    #[test]
+    fn test_poker_rank() {
+        let tests = [
+            ("2H3D5S9CKD", HandRank::HighCard),
+            ("2H2D5S9CKD", HandRank::OnePair),
+            ("2H3D4S5C6D", HandRank::Straight),
+            ("AH2D3S4C5D", HandRank::Straight),
+            ("THJDQSKCAD", HandRank::Straight),
+            ("2H4H6H8HTH", HandRank::Flush),
+            ("2H2D2S9C9D", HandRank::FullHouse),
+            ("2H2D2S2C9D", HandRank::FourOfAKind),
+            ("9HTHJHQHKH", HandRank::StraightFlush),
+            ("AH2H3H4H5H", HandRank::StraightFlush),
+            // Straights don't wrap around the ace
+            ("QHKDAS2C3D", HandRank::HighCard),
+        ];
+
+        for test in tests {
+            let hand = Hand::from(test.0);
+            assert_eq!(
+                Poker.rank(&hand),
+                test.1,
+                "{} should have rank {:?}",
+                test.0,
+                test.1
+            );
+        }
+    }
+
+    #[test]
+    fn test_poker_strength() {
+        let ace_low = Hand::from("AH2D3S4C5D");
+        let six_high = Hand::from("2H3D4S5C6D");
+        assert!(
+            Poker.strength(&ace_low) < Poker.strength(&six_high),
+            "An ace-low straight is the lowest straight"
+        );
+
+        let ace_kicker = Hand::from("2H8D8S4CAD");
+        let king_kicker = Hand::from("KH8D8S4C2D");
+        assert!(
+            Poker.strength(&king_kicker) < Poker.strength(&ace_kicker),
+            "Kickers are compared from the highest regardless of position"
+        );
+
+        let threes_full = Hand::from("2H2D3S3C3D");
+        let twos_full = Hand::from("3H3D2S2C2D");
+        assert!(
+            Poker.strength(&twos_full) < Poker.strength(&threes_full),
+            "The three of a kind is compared before the pair"
+        );
+
+        let same_values = Hand::from("AS8H2D4D8C");
+        assert_eq!(
+            Poker.strength(&ace_kicker),
+            Poker.strength(&same_values),
+            "Suits don't break ties"
+        );
+    }
+
+    #[test]
+    fn test_poker_total_winnings() {
+        let input = "2H3D4S5C6D 10
+        AH2D3S4C5D 20
+        KH8D8S4C2D 30";
+
+        let inputs = parse_hand_bids(input.lines());
+        assert_eq!(total_winnings(&inputs, &Poker), 30 + 20 * 2 + 10 * 3);
+    }
+
+    #[test]
+    fn test_display_hand() {
+        assert_eq!(Hand::from("32T3K").to_string(), "32T3K");
+        assert_eq!(Hand::from("AS2HTDTC3S").to_string(), "AS2HTDTC3S");
+    }
+
+    #[test]
+    fn test_explain_ranking() {
+        let input = "32T3K 765
+        T55J5 684
+        KK677 28
+        KTJJT 220
+        QQQJA 483";
+
+        let inputs = parse_hand_bids(input.lines());
+        let csv = explain_ranking(&inputs, &Jokers, ExplainFormat::Csv);
+        assert_eq!(
+            csv,
+            "position,hand,strengths,base_rank,rules_rank,bid,winnings
+1,32T3K,3 2 10 3 13,OnePair,OnePair,765,765
+2,KK677,13 13 6 7 7,TwoPair,TwoPair,28,56
+3,T55J5,10 5 5 1 5,ThreeOfAKind,FourOfAKind,684,2052
+4,QQQJA,12 12 12 1 14,ThreeOfAKind,FourOfAKind,483,1932
+5,KTJJT,13 10 1 1 10,TwoPair,FourOfAKind,220,1100
+"
+        );
+
+        let table = explain_ranking(&inputs, &Standard, ExplainFormat::Table);
+        assert_eq!(table.lines().count(), 6);
+        assert!(table.lines().nth(1).unwrap().contains("32T3K"));
+    }
+
+    #[test]
+    fn test_explain_poker_ranking() {
+        // A jack is a jack in poker, so the rank doesn't change
+        let inputs = parse_hand_bids(["JHJD2S2C5H 10"].iter());
+        let csv = explain_ranking(&inputs, &Poker, ExplainFormat::Csv);
+        assert!(
+            csv.ends_with(",TwoPair,TwoPair,10,10\n"),
+            "{} should rank two pair",
+            csv
+        );
+    }
+
     #[test]
     fn test_joker_total_winnings() {
         let input = "32T3K 765
@@ -389,6 +892,6 @@ mod test {
         QQQJA 483";
 
         let inputs = parse_hand_bids(input.lines());
-        assert_eq!(joker_total_winnings(&inputs), 5905);
+        assert_eq!(total_winnings(&inputs, &Jokers), 5905);
     }
 }
@@ -19,9 +19,34 @@ pub mod io {
     };
 
     pub fn read_lines(filename: &str) -> impl Iterator<Item = String> {
-        let file = File::open(filename).expect(format!("Could not open {}", &filename).as_str());
+        let file = File::open(filename).unwrap_or_else(|_| panic!("Could not open {}", &filename));
         BufReader::new(file)
             .lines()
             .map(|l| l.expect("Could not parse line"))
     }
 }
+
+pub mod random {
+    /// A xorshift generator, for reproducible test data and benchmarks. Not
+    /// for anything that needs good randomness.
+    pub struct XorShift(u64);
+
+    impl XorShift {
+        /// Xorshift gets stuck at zero, so a zero seed is replaced with one
+        pub fn new(seed: u64) -> XorShift {
+            XorShift(seed.max(1))
+        }
+
+        pub fn next_u64(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        /// A number in `0..bound`
+        pub fn below(&mut self, bound: usize) -> usize {
+            (self.next_u64() % bound as u64) as usize
+        }
+    }
+}